@@ -0,0 +1,185 @@
+// Derives the LMS matrices and the Brettel 1997 and Viénot 1999 dichromacy projections in f64,
+// and writes them as f32 constants to $OUT_DIR/cvd.rs, which lib.rs includes.
+// Change the inputs below and the constants follow.
+//
+// Although I am looking at the code in DaltonLens-Python,
+// My understanding of this code comes from this page:
+// https://daltonlens.org/understanding-cvd-simulation/
+// Accessed 2024-04-11.
+use std::fmt::Write;
+use std::path::Path;
+
+type Mat = [[f64; 3]; 3];
+type Vec3 = [f64; 3];
+
+// The inputs are from https://github.com/DaltonLens/DaltonLens-Python/blob/3cba5e6a7c8f0e8199c8f83f1afb58eb6dab7a3d/daltonlens/convert.py.
+
+// https://github.com/DaltonLens/DaltonLens-Python/blob/3cba5e6a7c8f0e8199c8f83f1afb58eb6dab7a3d/daltonlens/convert.py#L186
+const XYZ_JUDD_VOS_FROM_LINEAR_RGB_BT709: Mat = [
+    [40.9568e-2, 35.5041e-2, 17.9167e-2],
+    [21.3389e-2, 70.6743e-2, 7.98680e-2],
+    [1.86297e-2, 11.4620e-2, 91.2367e-2],
+];
+
+// https://github.com/DaltonLens/DaltonLens-Python/blob/3cba5e6a7c8f0e8199c8f83f1afb58eb6dab7a3d/daltonlens/convert.py#L160
+const LMS_FROM_XYZ_JUDD_VOS_SMITH_POKORNY_1975: Mat = [
+    [0.15514, 0.54312, -0.03286],
+    [-0.15514, 0.45684, 0.03286],
+    [0.0, 0.0, 0.01608],
+];
+
+// XYZ of the monochromatic anchors of each pair of half-planes.
+// https://github.com/DaltonLens/DaltonLens-Python/blob/3cba5e6a7c8f0e8199c8f83f1afb58eb6dab7a3d/daltonlens/simulate.py#L235
+const XYZ_475: Vec3 = [0.13287, 0.11284, 0.9422];
+const XYZ_575: Vec3 = [0.84394, 0.91558, 0.00197];
+const XYZ_485: Vec3 = [0.05699, 0.16987, 0.5864];
+const XYZ_660: Vec3 = [0.16161, 0.061, 0.00001];
+
+fn mul(m: &Mat, v: Vec3) -> Vec3 {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+fn mat_mul(a: &Mat, b: &Mat) -> Mat {
+    let mut out = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            out[i][j] = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+fn cross(u: Vec3, v: Vec3) -> Vec3 {
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+fn dot(u: Vec3, v: Vec3) -> f64 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
+// The rows of the inverse are the cross products of the columns, over the determinant.
+fn inverse(m: &Mat) -> Mat {
+    let col = |j: usize| [m[0][j], m[1][j], m[2][j]];
+    let rows = [
+        cross(col(1), col(2)),
+        cross(col(2), col(0)),
+        cross(col(0), col(1)),
+    ];
+    let det = dot(col(0), rows[0]);
+    rows.map(|row| row.map(|x| x / det))
+}
+
+// The missing cone `axis` of a point on the plane with normal `n`, from the other two cones.
+// https://github.com/DaltonLens/DaltonLens-Python/blob/3cba5e6a7c8f0e8199c8f83f1afb58eb6dab7a3d/daltonlens/simulate.py#L95
+fn projection(n: Vec3, axis: usize) -> Vec3 {
+    let mut row = n.map(|x| -x / n[axis]);
+    row[axis] = 0.0;
+    row
+}
+
+// https://github.com/DaltonLens/DaltonLens-Python/blob/3cba5e6a7c8f0e8199c8f83f1afb58eb6dab7a3d/daltonlens/simulate.py#L261
+fn brettel(white: Vec3, lms_on_wing1: Vec3, lms_on_wing2: Vec3, axis: usize) -> (Vec3, [Vec3; 2]) {
+    let n1 = cross(white, lms_on_wing1);
+    let n2 = cross(white, lms_on_wing2);
+    // The separation plane goes through the diagonal and the confusion axis.
+    let mut confusion_axis = [0.0; 3];
+    confusion_axis[axis] = 1.0;
+    let mut n_sep_plane = cross(white, confusion_axis);
+    // Instead of swapping the inputs so that wing 1 is on the positive side, negate the separation plane.
+    if dot(n_sep_plane, lms_on_wing1) < 0.0 {
+        n_sep_plane = n_sep_plane.map(|x| -x);
+    }
+    (n_sep_plane, [projection(n1, axis), projection(n2, axis)])
+}
+
+fn write_vec(out: &mut String, v: Vec3) {
+    write!(out, "[{:?}, {:?}, {:?}]", v[0], v[1], v[2]).unwrap();
+}
+
+fn write_mat(out: &mut String, name: &str, m: &Mat) {
+    write!(out, "const {}: [[f32; 3]; 3] = [", name).unwrap();
+    for row in m {
+        write_vec(out, *row);
+        out.push_str(", ");
+    }
+    out.push_str("];\n");
+}
+
+fn write_plane(out: &mut String, name: &str, plane: Vec3) {
+    write!(out, "const {}: [f32; 3] = ", name).unwrap();
+    write_vec(out, plane);
+    out.push_str(";\n");
+}
+
+fn write_brettel(
+    out: &mut String,
+    name: &str,
+    axis: usize,
+    (separation, planes): (Vec3, [Vec3; 2]),
+) {
+    write!(
+        out,
+        "const {}: Brettel = Brettel {{ axis: {}, separation: ",
+        name, axis
+    )
+    .unwrap();
+    write_vec(out, separation);
+    out.push_str(", planes: [");
+    write_vec(out, planes[0]);
+    out.push_str(", ");
+    write_vec(out, planes[1]);
+    out.push_str("] };\n");
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let lms_from_xyz = &LMS_FROM_XYZ_JUDD_VOS_SMITH_POKORNY_1975;
+    let lms_from_linear_rgb = mat_mul(lms_from_xyz, &XYZ_JUDD_VOS_FROM_LINEAR_RGB_BT709);
+    let linear_rgb_from_lms = inverse(&lms_from_linear_rgb);
+
+    // Using sRGB white. Mirror of https://github.com/DaltonLens/DaltonLens-Python/blob/3cba5e6a7c8f0e8199c8f83f1afb58eb6dab7a3d/daltonlens/simulate.py#L253
+    let white = mul(&lms_from_linear_rgb, [1.0, 1.0, 1.0]);
+    let lms_475 = mul(lms_from_xyz, XYZ_475);
+    let lms_575 = mul(lms_from_xyz, XYZ_575);
+    let lms_485 = mul(lms_from_xyz, XYZ_485);
+    let lms_660 = mul(lms_from_xyz, XYZ_660);
+
+    let mut out = String::from("// Generated by build.rs. Do not edit.\n");
+    write_mat(&mut out, "LMS_FROM_LINEAR_RGB", &lms_from_linear_rgb);
+    write_mat(&mut out, "LINEAR_RGB_FROM_LMS", &linear_rgb_from_lms);
+    write_brettel(
+        &mut out,
+        "BRETTEL_PROTAN",
+        0,
+        brettel(white, lms_475, lms_575, 0),
+    );
+    write_brettel(
+        &mut out,
+        "BRETTEL_DEUTAN",
+        1,
+        brettel(white, lms_475, lms_575, 1),
+    );
+    write_brettel(
+        &mut out,
+        "BRETTEL_TRITAN",
+        2,
+        brettel(white, lms_485, lms_660, 2),
+    );
+
+    // Viénot 1999 projects onto a single plane through white and a primary that dichromats see
+    // unchanged: blue for protans and deutans, and red for tritans, where the model is only a
+    // rough approximation. Mirror of DaltonLens-Python's Simulator_Vienot1999, in simulate.py.
+    let blue = mul(&lms_from_linear_rgb, [0.0, 0.0, 1.0]);
+    let red = mul(&lms_from_linear_rgb, [1.0, 0.0, 0.0]);
+    write_plane(&mut out, "VIENOT_PROTAN", projection(cross(white, blue), 0));
+    write_plane(&mut out, "VIENOT_DEUTAN", projection(cross(white, blue), 1));
+    write_plane(&mut out, "VIENOT_TRITAN", projection(cross(white, red), 2));
+
+    let path = Path::new(&std::env::var("OUT_DIR").unwrap()).join("cvd.rs");
+    std::fs::write(path, out).unwrap();
+}
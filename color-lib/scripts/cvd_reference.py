@@ -0,0 +1,40 @@
+# Expected values for test_cvd_reference in src/lib.rs, from DaltonLens-Python itself:
+#
+#     pip install daltonlens==0.1.5
+#     python3 color-lib/scripts/cvd_reference.py
+#
+# Simulator_Brettel1997 with its default Smith & Pokorny LMS model, which build.rs derives its
+# constants from. DaltonLens computes in float32 and clips to the gamut before encoding to 8 bits.
+
+from importlib.metadata import version
+
+import numpy as np
+from daltonlens import convert, simulate
+
+DALTONLENS_VERSION = "0.1.5"
+assert version("daltonlens") == DALTONLENS_VERSION, "Expected DaltonLens-Python " + DALTONLENS_VERSION
+
+DEFICIENCIES = [simulate.Deficiency.PROTAN, simulate.Deficiency.DEUTAN, simulate.Deficiency.TRITAN]
+COLORS = [
+    [0xFA, 0x35, 0xEC],
+    [0x3B, 0xDB, 0x78],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0x80, 0xFF],
+    [0xF7, 0xC6, 0x01],
+    [0x11, 0x22, 0x33],
+]
+
+
+def hex_list(c):
+    return "[%s]" % ", ".join("0x%02X" % v for v in c)
+
+
+simulator = simulate.Simulator_Brettel1997(convert.LMSModel_sRGB_SmithPokorny75())
+# A one-row image with every color.
+image = np.array([COLORS], dtype=np.uint8)
+simulated = [simulator.simulate_cvd(image, d, severity=1.0)[0] for d in DEFICIENCIES]
+for i, c in enumerate(COLORS):
+    print("(")
+    print("    sRGB(%s)," % hex_list(c))
+    print("    [%s]," % ", ".join(hex_list(s[i]) for s in simulated))
+    print("),")
@@ -0,0 +1,394 @@
+use crate::gamut::mul;
+use crate::{sRGB, Oklab, LMS, RGB};
+use crate::{BRETTEL_DEUTAN, BRETTEL_PROTAN, BRETTEL_TRITAN};
+use crate::{VIENOT_DEUTAN, VIENOT_PROTAN, VIENOT_TRITAN};
+use alloc::{format, string::String};
+
+// Published simulations of color vision deficiency. They mostly agree for protans and deutans
+// near gray, and differ most for tritans and saturated colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum CvdModel {
+    // Brettel, Viénot and Mollon 1997: two half-planes per deficiency. The `simulate_*` functions use this one.
+    Brettel1997,
+    // Viénot, Brettel and Mollon 1999: a single plane, good for protans and deutans but not tritans.
+    Vienot1999,
+    // Machado, Oliveira and Fernandes 2009: a matrix in linear RGB for each tenth of severity.
+    Machado2009,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Deficiency {
+    Protan,
+    Deutan,
+    Tritan,
+}
+
+// Matrices in linear sRGB for severities 0.0, 0.1, ..., 1.0, from the paper's supplementary table.
+// https://www.inf.ufrgs.br/~oliveira/pubs_files/CVD_Simulation/CVD_Simulation.html
+const MACHADO_PROTAN: [[[f32; 3]; 3]; 11] = [
+    [
+        [1.000000, 0.000000, 0.000000],
+        [0.000000, 1.000000, 0.000000],
+        [0.000000, 0.000000, 1.000000],
+    ],
+    [
+        [0.856167, 0.182038, -0.038205],
+        [0.029342, 0.955115, 0.015544],
+        [-0.002880, -0.001563, 1.004443],
+    ],
+    [
+        [0.734766, 0.334872, -0.069637],
+        [0.051840, 0.919198, 0.028963],
+        [-0.004928, -0.004209, 1.009137],
+    ],
+    [
+        [0.630323, 0.465641, -0.095964],
+        [0.069181, 0.890046, 0.040773],
+        [-0.006308, -0.007724, 1.014032],
+    ],
+    [
+        [0.539009, 0.579343, -0.118352],
+        [0.082546, 0.866121, 0.051332],
+        [-0.007136, -0.011959, 1.019095],
+    ],
+    [
+        [0.458064, 0.679578, -0.137642],
+        [0.092785, 0.846313, 0.060902],
+        [-0.007494, -0.016807, 1.024301],
+    ],
+    [
+        [0.385450, 0.769005, -0.154455],
+        [0.100526, 0.829802, 0.069673],
+        [-0.007442, -0.022190, 1.029632],
+    ],
+    [
+        [0.319627, 0.849633, -0.169261],
+        [0.106241, 0.815969, 0.077790],
+        [-0.007025, -0.028051, 1.035076],
+    ],
+    [
+        [0.259411, 0.923008, -0.182420],
+        [0.110296, 0.804340, 0.085364],
+        [-0.006276, -0.034346, 1.040622],
+    ],
+    [
+        [0.203876, 0.990338, -0.194214],
+        [0.112975, 0.794542, 0.092483],
+        [-0.005222, -0.041043, 1.046265],
+    ],
+    [
+        [0.152286, 1.052583, -0.204868],
+        [0.114503, 0.786281, 0.099216],
+        [-0.003882, -0.048116, 1.051998],
+    ],
+];
+const MACHADO_DEUTAN: [[[f32; 3]; 3]; 11] = [
+    [
+        [1.000000, 0.000000, 0.000000],
+        [0.000000, 1.000000, 0.000000],
+        [0.000000, 0.000000, 1.000000],
+    ],
+    [
+        [0.866435, 0.177704, -0.044139],
+        [0.049567, 0.939063, 0.011370],
+        [-0.003453, 0.007233, 0.996220],
+    ],
+    [
+        [0.760729, 0.319078, -0.079807],
+        [0.090568, 0.889315, 0.020117],
+        [-0.006027, 0.013325, 0.992702],
+    ],
+    [
+        [0.675425, 0.433850, -0.109275],
+        [0.125303, 0.847755, 0.026942],
+        [-0.007950, 0.018572, 0.989378],
+    ],
+    [
+        [0.605511, 0.528560, -0.134071],
+        [0.155318, 0.812366, 0.032316],
+        [-0.009376, 0.023176, 0.986200],
+    ],
+    [
+        [0.547494, 0.607765, -0.155259],
+        [0.181692, 0.781742, 0.036566],
+        [-0.010410, 0.027275, 0.983136],
+    ],
+    [
+        [0.498864, 0.674741, -0.173604],
+        [0.205199, 0.754872, 0.039929],
+        [-0.011131, 0.030969, 0.980162],
+    ],
+    [
+        [0.457771, 0.731899, -0.189670],
+        [0.226409, 0.731012, 0.042579],
+        [-0.011595, 0.034333, 0.977261],
+    ],
+    [
+        [0.422823, 0.781057, -0.203881],
+        [0.245752, 0.709602, 0.044646],
+        [-0.011843, 0.037423, 0.974421],
+    ],
+    [
+        [0.392952, 0.823610, -0.216562],
+        [0.263559, 0.690210, 0.046232],
+        [-0.011910, 0.040281, 0.971630],
+    ],
+    [
+        [0.367322, 0.860646, -0.227968],
+        [0.280085, 0.672501, 0.047413],
+        [-0.011820, 0.042940, 0.968881],
+    ],
+];
+const MACHADO_TRITAN: [[[f32; 3]; 3]; 11] = [
+    [
+        [1.000000, 0.000000, 0.000000],
+        [0.000000, 1.000000, 0.000000],
+        [0.000000, 0.000000, 1.000000],
+    ],
+    [
+        [0.926670, 0.092514, -0.019184],
+        [0.021191, 0.964503, 0.014306],
+        [0.008437, 0.054813, 0.936750],
+    ],
+    [
+        [0.895720, 0.133330, -0.029050],
+        [0.029997, 0.945400, 0.024603],
+        [0.013027, 0.104707, 0.882266],
+    ],
+    [
+        [0.905871, 0.127791, -0.033662],
+        [0.026856, 0.941251, 0.031893],
+        [0.013410, 0.148296, 0.838294],
+    ],
+    [
+        [0.948035, 0.089490, -0.037526],
+        [0.014364, 0.946792, 0.038844],
+        [0.010853, 0.193991, 0.795156],
+    ],
+    [
+        [1.017277, 0.027029, -0.044306],
+        [-0.006113, 0.958479, 0.047634],
+        [0.006379, 0.248708, 0.744913],
+    ],
+    [
+        [1.104996, -0.046633, -0.058363],
+        [-0.032137, 0.971635, 0.060503],
+        [0.001336, 0.317922, 0.680742],
+    ],
+    [
+        [1.193214, -0.109812, -0.083402],
+        [-0.058496, 0.979410, 0.079086],
+        [-0.002346, 0.403492, 0.598854],
+    ],
+    [
+        [1.257728, -0.139648, -0.118081],
+        [-0.078003, 0.975409, 0.102594],
+        [-0.003316, 0.501214, 0.502102],
+    ],
+    [
+        [1.278864, -0.125333, -0.153531],
+        [-0.084748, 0.957674, 0.127074],
+        [-0.000989, 0.601151, 0.399838],
+    ],
+    [
+        [1.255528, -0.076749, -0.178779],
+        [-0.078411, 0.930809, 0.147602],
+        [0.004733, 0.691367, 0.303900],
+    ],
+];
+
+// Machado's matrix for `severity`, interpolated between the two nearest tabulated ones.
+fn machado_matrix(kind: Deficiency, severity: f32) -> [[f32; 3]; 3] {
+    let table = match kind {
+        Deficiency::Protan => &MACHADO_PROTAN,
+        Deficiency::Deutan => &MACHADO_DEUTAN,
+        Deficiency::Tritan => &MACHADO_TRITAN,
+    };
+    let x = severity * 10.0;
+    let lower = (x as usize).min(9);
+    let t = x - lower as f32;
+    let (m1, m2) = (&table[lower], &table[lower + 1]);
+    core::array::from_fn(|i| core::array::from_fn(|j| (1.0 - t) * m1[i][j] + t * m2[i][j]))
+}
+
+impl CvdModel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CvdModel::Brettel1997 => "brettel1997",
+            CvdModel::Vienot1999 => "vienot1999",
+            CvdModel::Machado2009 => "machado2009",
+        }
+    }
+
+    // Dichromacy, in linear RGB, for the models that only simulate that.
+    fn simulate_dichromacy(&self, kind: Deficiency, c: RGB) -> RGB {
+        match (self, kind) {
+            (CvdModel::Brettel1997, Deficiency::Protan) => {
+                LMS::from(c).simulate(&BRETTEL_PROTAN).into()
+            }
+            (CvdModel::Brettel1997, Deficiency::Deutan) => {
+                LMS::from(c).simulate(&BRETTEL_DEUTAN).into()
+            }
+            (CvdModel::Brettel1997, Deficiency::Tritan) => {
+                LMS::from(c).simulate(&BRETTEL_TRITAN).into()
+            }
+            (CvdModel::Vienot1999, Deficiency::Protan) => {
+                LMS::from(c).project(0, VIENOT_PROTAN).into()
+            }
+            (CvdModel::Vienot1999, Deficiency::Deutan) => {
+                LMS::from(c).project(1, VIENOT_DEUTAN).into()
+            }
+            (CvdModel::Vienot1999, Deficiency::Tritan) => {
+                LMS::from(c).project(2, VIENOT_TRITAN).into()
+            }
+            (CvdModel::Machado2009, _) => unreachable!("Machado has its own partial severities"),
+        }
+    }
+
+    // Machado interpolates its tabulated matrices, like DaltonLens does.
+    // Brettel and Viénot blend linearly towards dichromacy in linear RGB, which is exact at
+    // severity 1.0, so that matches the dichromacy simulations.
+    fn simulate_rgb(&self, kind: Deficiency, severity: f32, c: RGB) -> Oklab {
+        assert!(
+            (0.0..=1.0).contains(&severity),
+            "Severity must be between 0 and 1"
+        );
+        if *self == CvdModel::Machado2009 {
+            let [r, g, b] = mul(&machado_matrix(kind, severity), [c.r, c.g, c.b]);
+            return RGB { r: r, g: g, b: b }.into();
+        }
+        let sim = self.simulate_dichromacy(kind, c);
+        let blend = |x: f32, y: f32| severity * y + (1.0 - severity) * x;
+        RGB {
+            r: blend(c.r, sim.r),
+            g: blend(c.g, sim.g),
+            b: blend(c.b, sim.b),
+        }
+        .into()
+    }
+}
+
+impl core::str::FromStr for CvdModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "brettel" | "brettel1997" => Ok(CvdModel::Brettel1997),
+            "vienot" | "vienot1999" | "viénot" | "viénot1999" => Ok(CvdModel::Vienot1999),
+            "machado" | "machado2009" => Ok(CvdModel::Machado2009),
+            _ => Err(format!("Unknown CVD model {}", s)),
+        }
+    }
+}
+
+impl core::str::FromStr for Deficiency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "protan" | "protanopia" | "protanomaly" => Ok(Deficiency::Protan),
+            "deutan" | "deuteranopia" | "deuteranomaly" => Ok(Deficiency::Deutan),
+            "tritan" | "tritanopia" | "tritanomaly" => Ok(Deficiency::Tritan),
+            _ => Err(format!("Unknown deficiency {}", s)),
+        }
+    }
+}
+
+// How `c` looks with `kind` of color vision deficiency, as simulated by `model`.
+// `severity` goes from 0 for normal vision to 1 for dichromacy.
+pub fn simulate(model: CvdModel, kind: Deficiency, severity: f32, c: sRGB) -> Oklab {
+    model.simulate_rgb(kind, severity, RGB::from(c))
+}
+
+impl Oklab {
+    pub fn simulate(&self, model: CvdModel, kind: Deficiency, severity: f32) -> Oklab {
+        model.simulate_rgb(kind, severity, RGB::from(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simulate_deutan, simulate_protan, simulate_tritan, HyAB};
+    use itertools::iproduct;
+
+    const MODELS: [CvdModel; 3] = [
+        CvdModel::Brettel1997,
+        CvdModel::Vienot1999,
+        CvdModel::Machado2009,
+    ];
+    const KINDS: [Deficiency; 3] = [Deficiency::Protan, Deficiency::Deutan, Deficiency::Tritan];
+
+    #[test]
+    fn test_models() {
+        for (r, g, b) in iproduct!(
+            (0..=255).step_by(15),
+            (0..=255).step_by(15),
+            (0..=255).step_by(15)
+        ) {
+            let c = sRGB([r, g, b]);
+            let lab = Oklab::from(c);
+            // Severity 1 is exactly the existing Brettel simulations.
+            let brettel = KINDS.map(|kind| simulate(CvdModel::Brettel1997, kind, 1.0, c));
+            assert_eq!(
+                brettel,
+                [simulate_protan(c), simulate_deutan(c), simulate_tritan(c)]
+            );
+            for (model, kind) in iproduct!(MODELS, KINDS) {
+                assert!(HyAB(&lab.simulate(model, kind, 0.0), &lab) < 1e-3);
+                let sim = simulate(model, kind, 1.0, c);
+                assert!(HyAB(&lab.simulate(model, kind, 1.0), &sim) < 1e-2);
+            }
+        }
+
+        // All models keep grays, and Viénot keeps the primary its plane goes through.
+        for (model, kind, x) in iproduct!(MODELS, KINDS, (0..=255).step_by(5)) {
+            let gray = Oklab::from(sRGB([x, x, x]));
+            assert!(HyAB(&gray.simulate(model, kind, 1.0), &gray) < 1e-3);
+            assert!(HyAB(&gray.simulate(model, kind, 0.5), &gray) < 1e-3);
+        }
+        let (red, blue) = (
+            Oklab::from(sRGB([255, 0, 0])),
+            Oklab::from(sRGB([0, 0, 255])),
+        );
+        for (kind, c) in [
+            (Deficiency::Protan, blue),
+            (Deficiency::Deutan, blue),
+            (Deficiency::Tritan, red),
+        ] {
+            assert!(HyAB(&c.simulate(CvdModel::Vienot1999, kind, 1.0), &c) < 1e-3);
+        }
+
+        // Machado protanopia makes pure red a dark olive: the first column of its matrix.
+        assert_eq!(
+            simulate(
+                CvdModel::Machado2009,
+                Deficiency::Protan,
+                1.0,
+                sRGB([255, 0, 0])
+            )
+            .to_sRGB(),
+            sRGB([0x6D, 0x5F, 0x00])
+        );
+
+        // Partial Machado severities use the tabulated matrices, and interpolate between them.
+        assert_eq!(machado_matrix(Deficiency::Deutan, 0.5), MACHADO_DEUTAN[5]);
+        let m = machado_matrix(Deficiency::Tritan, 0.75);
+        for (i, j) in iproduct!(0..3, 0..3) {
+            let mid = (MACHADO_TRITAN[7][i][j] + MACHADO_TRITAN[8][i][j]) / 2.0;
+            assert!((m[i][j] - mid).abs() < 1e-5);
+        }
+        assert_eq!("Vienot".parse(), Ok(CvdModel::Vienot1999));
+        assert_eq!("deuteranomaly".parse(), Ok(Deficiency::Deutan));
+    }
+}
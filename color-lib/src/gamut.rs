@@ -0,0 +1,234 @@
+use crate::math::{powf, round};
+use crate::{sRGB, Oklab, RGB};
+use alloc::{format, string::String};
+
+// RGB color spaces, all with the D65 white point of sRGB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Gamut {
+    Srgb,
+    // Same transfer function as sRGB, with wider primaries.
+    DisplayP3,
+    Rec2020,
+}
+
+// Matrices computed from the primaries and white point of each standard.
+const P3_TO_SRGB: [[f32; 3]; 3] = [
+    [1.2249401763, -0.2249401763, 0.0],
+    [-0.0420569547, 1.0420569547, 0.0],
+    [-0.0196375546, -0.0786360456, 1.0982736001],
+];
+const SRGB_TO_P3: [[f32; 3]; 3] = [
+    [0.8224619687, 0.1775380313, 0.0],
+    [0.0331941989, 0.9668058011, 0.0],
+    [0.0170826307, 0.0723974407, 0.9105199286],
+];
+const REC2020_TO_SRGB: [[f32; 3]; 3] = [
+    [1.6604910021, -0.5876411388, -0.0728498633],
+    [-0.1245504745, 1.1328998971, -0.0083494226],
+    [-0.0181507634, -0.1005788980, 1.1187296614],
+];
+const SRGB_TO_REC2020: [[f32; 3]; 3] = [
+    [0.6274038959, 0.3292830384, 0.0433130657],
+    [0.0690972894, 0.9195403951, 0.0113623156],
+    [0.0163914389, 0.0880133079, 0.8955952532],
+];
+
+// Constants of the Rec. 2020 transfer function, at full precision.
+const REC2020_ALPHA: f32 = 1.0992968;
+const REC2020_BETA: f32 = 0.01805397;
+
+pub(crate) fn mul(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+impl Gamut {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gamut::Srgb => "srgb",
+            Gamut::DisplayP3 => "display-p3",
+            Gamut::Rec2020 => "rec2020",
+        }
+    }
+
+    // Linear to gamma-encoded. Negative values are mirrored, so out-of-gamut colors survive a round trip.
+    pub(crate) fn encode(&self, x: f32) -> f32 {
+        let y = x.abs();
+        let encoded = match self {
+            Gamut::Srgb | Gamut::DisplayP3 if y <= 0.0031308 => 12.92 * y,
+            Gamut::Srgb | Gamut::DisplayP3 => 1.055 * powf(y, 1.0 / 2.4) - 0.055,
+            Gamut::Rec2020 if y < REC2020_BETA => 4.5 * y,
+            Gamut::Rec2020 => REC2020_ALPHA * powf(y, 0.45) - (REC2020_ALPHA - 1.0),
+        };
+        encoded.copysign(x)
+    }
+
+    pub(crate) fn decode(&self, x: f32) -> f32 {
+        let y = x.abs();
+        let decoded = match self {
+            Gamut::Srgb | Gamut::DisplayP3 if y <= 0.04045 => y / 12.92,
+            Gamut::Srgb | Gamut::DisplayP3 => powf((y + 0.055) / 1.055, 2.4),
+            Gamut::Rec2020 if y < 4.5 * REC2020_BETA => y / 4.5,
+            Gamut::Rec2020 => powf((y + REC2020_ALPHA - 1.0) / REC2020_ALPHA, 1.0 / 0.45),
+        };
+        decoded.copysign(x)
+    }
+
+    fn linear_to_srgb(&self, linear: [f32; 3]) -> [f32; 3] {
+        match self {
+            Gamut::Srgb => linear,
+            Gamut::DisplayP3 => mul(&P3_TO_SRGB, linear),
+            Gamut::Rec2020 => mul(&REC2020_TO_SRGB, linear),
+        }
+    }
+
+    fn linear_from_srgb(&self, linear: [f32; 3]) -> [f32; 3] {
+        match self {
+            Gamut::Srgb => linear,
+            Gamut::DisplayP3 => mul(&SRGB_TO_P3, linear),
+            Gamut::Rec2020 => mul(&SRGB_TO_REC2020, linear),
+        }
+    }
+}
+
+impl core::str::FromStr for Gamut {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "srgb" => Ok(Gamut::Srgb),
+            "p3" | "display-p3" => Ok(Gamut::DisplayP3),
+            "rec2020" | "rec.2020" => Ok(Gamut::Rec2020),
+            _ => Err(format!("Unknown gamut {}", s)),
+        }
+    }
+}
+
+// A gamma-encoded color in `gamut`, with channels from 0 to 1 at any bit depth.
+// Channels outside 0 to 1 mean the color is outside the gamut.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RgbColor {
+    pub gamut: Gamut,
+    pub rgb: [f32; 3],
+}
+
+impl RgbColor {
+    pub fn new(gamut: Gamut, rgb: [f32; 3]) -> Self {
+        Self {
+            gamut: gamut,
+            rgb: rgb,
+        }
+    }
+
+    // Integer channels of `bits` bits, like 10-bit video.
+    pub fn from_bits(gamut: Gamut, rgb: [u16; 3], bits: u32) -> Self {
+        let max = ((1 << bits) - 1) as f32;
+        Self::new(gamut, rgb.map(|x| x as f32 / max))
+    }
+
+    // The same codes as an `sRGB`, but in any gamut.
+    pub fn from_8bit(gamut: Gamut, c: sRGB) -> Self {
+        Self::from_bits(gamut, c.0.map(u16::from), 8)
+    }
+
+    // Rounds to the nearest `bits`-bit color, clipping each channel to the gamut.
+    pub fn to_bits(&self, bits: u32) -> [u16; 3] {
+        let max = ((1 << bits) - 1) as f32;
+        self.rgb.map(|x| round(x.clamp(0.0, 1.0) * max) as u16)
+    }
+
+    pub fn to_8bit(&self) -> sRGB {
+        sRGB(self.to_bits(8).map(|x| x as u8))
+    }
+
+    pub fn in_gamut(&self) -> bool {
+        // Enough slack for rounding errors in conversions.
+        const EPS: f32 = 1e-4;
+        self.rgb.iter().all(|x| (-EPS..=1.0 + EPS).contains(x))
+    }
+
+    // The same color in another gamut, which may be outside it.
+    pub fn convert(&self, gamut: Gamut) -> Self {
+        let linear = self.rgb.map(|x| self.gamut.decode(x));
+        let linear = gamut.linear_from_srgb(self.gamut.linear_to_srgb(linear));
+        Self::new(gamut, linear.map(|x| gamut.encode(x)))
+    }
+
+    // The closest 8-bit sRGB color, for displays without a wider gamut. Out-of-gamut colors are clipped.
+    pub fn srgb_fallback(&self) -> sRGB {
+        self.convert(Gamut::Srgb).to_8bit()
+    }
+}
+
+impl From<sRGB> for RgbColor {
+    fn from(c: sRGB) -> Self {
+        Self::from_8bit(Gamut::Srgb, c)
+    }
+}
+
+impl From<RgbColor> for Oklab {
+    fn from(c: RgbColor) -> Self {
+        let linear = c.gamut.linear_to_srgb(c.rgb.map(|x| c.gamut.decode(x)));
+        RGB {
+            r: linear[0],
+            g: linear[1],
+            b: linear[2],
+        }
+        .into()
+    }
+}
+
+impl Oklab {
+    pub fn to_rgb_color(&self, gamut: Gamut) -> RgbColor {
+        let linear = gamut.linear_from_srgb(self.linear_rgb());
+        RgbColor::new(gamut, linear.map(|x| gamut.encode(x)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_gamuts() {
+        for c in [
+            sRGB([0x00, 0x00, 0x00]),
+            sRGB([0xFF, 0xFF, 0xFF]),
+            sRGB([0xFA, 0x35, 0xEC]),
+            sRGB([0x3B, 0xDB, 0x78]),
+        ] {
+            let oklab = Oklab::from(c);
+            let via_rgb = Oklab::from(RgbColor::from(c));
+            assert_abs_diff_eq!(via_rgb.L, oklab.L, epsilon = 1e-3);
+            assert_abs_diff_eq!(via_rgb.a, oklab.a, epsilon = 1e-3);
+            assert_abs_diff_eq!(via_rgb.b, oklab.b, epsilon = 1e-3);
+            for gamut in [Gamut::DisplayP3, Gamut::Rec2020] {
+                // sRGB fits inside both wider gamuts.
+                let wide = oklab.to_rgb_color(gamut);
+                assert!(wide.in_gamut());
+                assert_eq!(wide.srgb_fallback(), c);
+                assert_eq!(
+                    RgbColor::from_bits(gamut, wide.to_bits(10), 10).srgb_fallback(),
+                    c
+                );
+            }
+        }
+
+        let p3_red = RgbColor::new(Gamut::DisplayP3, [1.0, 0.0, 0.0]);
+        assert!(!p3_red.convert(Gamut::Srgb).in_gamut());
+        assert_eq!(p3_red.srgb_fallback(), sRGB([0xFF, 0x00, 0x00]));
+        let p3_green = RgbColor::new(Gamut::DisplayP3, [0.0, 1.0, 0.0]);
+        assert!(!p3_green.convert(Gamut::Srgb).in_gamut());
+        assert!(p3_green.convert(Gamut::Rec2020).in_gamut());
+        assert_eq!(
+            RgbColor::from_8bit(Gamut::Rec2020, sRGB([0xFF; 3])).srgb_fallback(),
+            sRGB([0xFF; 3])
+        );
+    }
+}
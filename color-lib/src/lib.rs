@@ -1,21 +1,78 @@
-use fast_srgb8::srgb8_to_f32;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("color-lib needs either the `std` or the `libm` feature");
+
+extern crate alloc;
+
+#[cfg(feature = "fast-srgb8")]
+use fast_srgb8::{f32_to_srgb8, srgb8_to_f32};
+use math::{atan2, cbrt, powf, round, sqrt};
+
+mod css;
+mod cvd;
+mod gamut;
+mod math;
+mod srgb;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use cvd::{simulate, CvdModel, Deficiency};
+use gamut::mul;
+pub use gamut::{Gamut, RgbColor};
+pub use srgb::{as_index, sRGB, ParseColorError};
+
+// Exact versions of the fast-srgb8 conversions. Decoding agrees with its table to a few ulps,
+// and encoding gives the same 8-bit value, but results computed from them can round differently.
+#[cfg(not(feature = "fast-srgb8"))]
+use {exact_f32_to_srgb8 as f32_to_srgb8, exact_srgb8_to_f32 as srgb8_to_f32};
+
+#[cfg_attr(feature = "fast-srgb8", allow(dead_code))]
+fn exact_srgb8_to_f32(c: u8) -> f32 {
+    Gamut::Srgb.decode(c as f32 / 255.0)
+}
+
+#[cfg_attr(feature = "fast-srgb8", allow(dead_code))]
+fn exact_f32_to_srgb8(x: f32) -> u8 {
+    round(Gamut::Srgb.encode(x.clamp(0.0, 1.0)) * 255.0) as u8
+}
 
-#[allow(non_camel_case_types)]
-pub type sRGB = [u8; 3];
+// An sRGB color with straight (not premultiplied) alpha, from 0 for transparent to 1 for opaque.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rgba {
+    pub rgb: sRGB,
+    pub alpha: f32,
+}
+
+impl Rgba {
+    pub fn new(rgb: sRGB, alpha: f32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&alpha),
+            "Alpha must be between 0 and 1"
+        );
+        Self {
+            rgb: rgb,
+            alpha: alpha,
+        }
+    }
 
-pub fn as_index(c: &sRGB) -> usize {
-    // RGB order. Might change later.
-    let mut out: usize = c[2] as usize;
-    out |= (c[1] as usize) << 8;
-    out |= (c[0] as usize) << 16;
-    out
+    // The opaque color seen when this is drawn over `bg`.
+    // Like browsers and most UI toolkits, this blends the gamma-encoded values.
+    pub fn over(&self, bg: &sRGB) -> sRGB {
+        sRGB([0, 1, 2].map(|k| {
+            round(self.alpha * self.rgb[k] as f32 + (1.0 - self.alpha) * bg[k] as f32) as u8
+        }))
+    }
 }
 
-pub fn to_string(c: &sRGB) -> String {
-    format!("#{:06x}", as_index(c)).to_uppercase()
+impl From<sRGB> for Rgba {
+    fn from(c: sRGB) -> Self {
+        Self::new(c, 1.0)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 struct RGB {
     r: f32,
     g: f32,
@@ -34,10 +91,11 @@ impl From<sRGB> for RGB {
 
 #[allow(non_snake_case)]
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Oklab {
-    L: f32,
-    a: f32,
-    b: f32,
+    pub L: f32,
+    pub a: f32,
+    pub b: f32,
 }
 
 // This is a scale factor to make it roughly line up with CIELAB.
@@ -52,9 +110,9 @@ impl From<RGB> for Oklab {
         let m = 0.2119034982 * c.r + 0.6806995451 * c.g + 0.1073969566 * c.b;
         let s = 0.0883024619 * c.r + 0.2817188376 * c.g + 0.6299787005 * c.b;
 
-        let l_ = l.cbrt();
-        let m_ = m.cbrt();
-        let s_ = s.cbrt();
+        let l_ = cbrt(l);
+        let m_ = cbrt(m);
+        let s_ = cbrt(s);
 
         Oklab {
             L: (0.2104542553 * OKLAB_SCALE) * l_ + (0.7936177850 * OKLAB_SCALE) * m_
@@ -73,25 +131,83 @@ impl From<sRGB> for Oklab {
     }
 }
 
+impl From<Oklab> for RGB {
+    #[allow(non_snake_case)]
+    fn from(c: Oklab) -> Self {
+        // Copied from https://bottosson.github.io/posts/oklab/#converting-from-linear-srgb-to-oklab.
+        // Accessed 2024-01-30.
+        let (L, a, b) = (c.L / OKLAB_SCALE, c.a / OKLAB_SCALE, c.b / OKLAB_SCALE);
+        let l_ = L + 0.3963377774 * a + 0.2158037573 * b;
+        let m_ = L - 0.1055613458 * a - 0.0638541728 * b;
+        let s_ = L - 0.0894841775 * a - 1.2914855480 * b;
+
+        let l = l_ * l_ * l_;
+        let m = m_ * m_ * m_;
+        let s = s_ * s_ * s_;
+
+        RGB {
+            r: 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            g: -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            b: -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+        }
+    }
+}
+
+// Oklab colors don't have to be exactly representable in 8 bits, or even inside the sRGB gamut.
+impl Oklab {
+    pub fn linear_rgb(&self) -> [f32; 3] {
+        let c = RGB::from(*self);
+        [c.r, c.g, c.b]
+    }
+
+    pub fn in_gamut(&self) -> bool {
+        // Enough slack for colors converted from sRGB to count as inside.
+        const EPS: f32 = 1e-4;
+        self.linear_rgb()
+            .iter()
+            .all(|x| (-EPS..=1.0 + EPS).contains(x))
+    }
+
+    // The nearest 8-bit color, clamping to the gamut first.
+    #[allow(non_snake_case)]
+    pub fn to_sRGB(&self) -> sRGB {
+        sRGB(self.linear_rgb().map(f32_to_srgb8))
+    }
+
+    pub fn simulate_protan(&self) -> Oklab {
+        LMS::from(RGB::from(*self)).simulate_protan().into()
+    }
+
+    pub fn simulate_deutan(&self) -> Oklab {
+        LMS::from(RGB::from(*self)).simulate_deutan().into()
+    }
+
+    pub fn simulate_tritan(&self) -> Oklab {
+        LMS::from(RGB::from(*self)).simulate_tritan().into()
+    }
+}
+
 #[allow(non_snake_case)]
 pub fn HyAB(c1: &Oklab, c2: &Oklab) -> f32 {
-    return (c1.L - c2.L).abs() + ((c1.a - c2.a).powi(2) + (c1.b - c2.b).powi(2)).sqrt();
+    let (da, db) = (c1.a - c2.a, c1.b - c2.b);
+    return (c1.L - c2.L).abs() + sqrt(da * da + db * db);
 }
 
+// `c` is gamma-encoded sRGB, from 0 to 1.
 #[allow(non_snake_case)]
-fn apca_luminance(c: &sRGB) -> f32 {
+fn apca_luminance(c: [f32; 3]) -> f32 {
     const S_TRC: f32 = 2.4;
     const B_THRSH: f32 = 0.022;
     const B_CLIP: f32 = 1.414;
 
-    let Y_c = ((c[0] as f32) / 255.0).powf(S_TRC) * 0.2126729
-        + ((c[1] as f32) / 255.0).powf(S_TRC) * 0.7151522
-        + ((c[2] as f32) / 255.0).powf(S_TRC) * 0.0721750;
+    let Y_c = powf(c[0], S_TRC) * 0.2126729
+        + powf(c[1], S_TRC) * 0.7151522
+        + powf(c[2], S_TRC) * 0.0721750;
 
     if Y_c < 0.0 {
         0.0
     } else if Y_c < B_THRSH {
-        Y_c + (B_THRSH - Y_c).powf(B_CLIP)
+        Y_c + powf(B_THRSH - Y_c, B_CLIP)
     } else {
         Y_c
     }
@@ -101,6 +217,21 @@ fn apca_luminance(c: &sRGB) -> f32 {
 // Accessed 2024-03-19.
 #[allow(non_snake_case)]
 pub fn APCA(text: &sRGB, bg: &sRGB) -> f32 {
+    apca_contrast(
+        apca_luminance(text.0.map(|x| x as f32 / 255.0)),
+        apca_luminance(bg.0.map(|x| x as f32 / 255.0)),
+    )
+}
+
+// Same as `APCA`, for colors between 8-bit values. Out-of-gamut colors are clamped.
+#[allow(non_snake_case)]
+pub fn APCA_Oklab(text: &Oklab, bg: &Oklab) -> f32 {
+    let encode = |c: &Oklab| c.to_rgb_color(Gamut::Srgb).rgb.map(|x| x.clamp(0.0, 1.0));
+    apca_contrast(apca_luminance(encode(text)), apca_luminance(encode(bg)))
+}
+
+#[allow(non_snake_case)]
+fn apca_contrast(Y_txt: f32, Y_bg: f32) -> f32 {
     const NTX: f32 = 0.57;
     const NBG: f32 = 0.56;
     const RTX: f32 = 0.62;
@@ -108,13 +239,10 @@ pub fn APCA(text: &sRGB, bg: &sRGB) -> f32 {
     const W_SCALE: f32 = 1.14;
     const W_OFFSET: f32 = 0.027;
 
-    let Y_txt = apca_luminance(text);
-    let Y_bg = apca_luminance(bg);
-
     let S_apc = if Y_txt < Y_bg {
-        Y_bg.powf(NBG) - Y_txt.powf(NTX)
+        powf(Y_bg, NBG) - powf(Y_txt, NTX)
     } else {
-        Y_bg.powf(RBG) - Y_txt.powf(RTX)
+        powf(Y_bg, RBG) - powf(Y_txt, RTX)
     } * W_SCALE;
 
     if S_apc.abs() < W_OFFSET {
@@ -135,12 +263,8 @@ struct LMS {
 
 impl From<RGB> for LMS {
     fn from(c: RGB) -> Self {
-        // Matrix derived in src/color_derivations.jl. Result of line 29.
-        LMS {
-            l: 0.178824041258 * c.r + 0.43516090570 * c.g + 0.04119349692 * c.b,
-            m: 0.034556423182 * c.r + 0.27155382458 * c.g + 0.03867130836 * c.b,
-            s: 0.000299565576 * c.r + 0.00184308960 * c.g + 0.01467086130 * c.b,
-        }
+        let [l, m, s] = mul(&LMS_FROM_LINEAR_RGB, [c.r, c.g, c.b]);
+        LMS { l: l, m: m, s: s }
     }
 }
 
@@ -152,12 +276,8 @@ impl From<sRGB> for LMS {
 
 impl From<LMS> for RGB {
     fn from(c: LMS) -> Self {
-        // Matrix derived in src/color_derivations.jl. Result of line 33.
-        RGB {
-            r: 8.09443559803237000 * c.l - 13.050431460496924 * c.m + 11.672058453917323 * c.s,
-            g: -1.02485055866466830 * c.l + 5.4019313096749730 * c.m - 11.361471490598714 * c.s,
-            b: -0.03652974715933317 * c.l - 0.4121628070012680 * c.m + 69.351324238208580 * c.s,
-        }
+        let [r, g, b] = mul(&LINEAR_RGB_FROM_LMS, [c.l, c.m, c.s]);
+        RGB { r: r, g: g, b: b }
     }
 }
 
@@ -167,47 +287,54 @@ impl From<LMS> for Oklab {
     }
 }
 
+// Brettel 1997 simulation of a missing cone: colors on the separation plane or its positive side
+// are projected onto the first half-plane, and the rest onto the second, as in DaltonLens. Each
+// plane gives the missing cone from the other two, with a zero for itself.
+struct Brettel {
+    axis: usize,
+    separation: [f32; 3],
+    planes: [[f32; 3]; 2],
+}
+
+// The LMS matrices, and the Brettel and Viénot planes, derived by build.rs.
+include!(concat!(env!("OUT_DIR"), "/cvd.rs"));
+
+fn dot(u: [f32; 3], v: [f32; 3]) -> f32 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
 impl LMS {
-    // Derived in src/color_derivations.jl. Result of line 116.
-    fn simulate_protan(self) -> Self {
-        let l = if -0.016813516536 * self.m + 0.344781556122 * self.s > 0.0 {
-            2.1683061543738997 * self.m - 5.496382983183359 * self.s
-        } else {
-            2.18614812275877 * self.m - 5.862254192269454 * self.s
-        };
+    // Replaces the missing cone `axis` to put the color on `plane`, given as in `Brettel`.
+    fn project(self, axis: usize, plane: [f32; 3]) -> Self {
+        let mut c = [self.l, self.m, self.s];
+        c[axis] = dot(plane, c);
         LMS {
-            l: l,
-            m: self.m,
-            s: self.s,
+            l: c[0],
+            m: c[1],
+            s: c[2],
         }
     }
 
-    // Derived in src/color_derivations.jl. Result of line 117.
-    fn simulate_deutan(self) -> Self {
-        let m = if -0.016813516536 * self.l + 0.655178443878 * self.s > 0.0 {
-            0.4611894856189028 * self.l + 2.5348740407788237 * self.s
+    fn simulate(self, brettel: &Brettel) -> Self {
+        let c = [self.l, self.m, self.s];
+        let plane = if dot(brettel.separation, c) >= 0.0 {
+            brettel.planes[0]
         } else {
-            0.45742554659931645 * self.l + 2.681544828202989 * self.s
+            brettel.planes[1]
         };
-        LMS {
-            l: self.l,
-            m: m,
-            s: self.s,
-        }
+        self.project(brettel.axis, plane)
+    }
+
+    fn simulate_protan(self) -> Self {
+        self.simulate(&BRETTEL_PROTAN)
+    }
+
+    fn simulate_deutan(self) -> Self {
+        self.simulate(&BRETTEL_DEUTAN)
     }
 
-    // Derived in src/color_derivations.jl. Result of line 118.
     fn simulate_tritan(self) -> Self {
-        let s = if -0.344781556121 * self.l + 0.655178443878 * self.m > 0.0 {
-            -0.06010959444193691 * self.l + 0.1629902356630733 * self.m
-        } else {
-            -0.002574363979654964 * self.l + 0.05365769715251148 * self.m
-        };
-        LMS {
-            l: self.l,
-            m: self.m,
-            s: s,
-        }
+        self.simulate(&BRETTEL_TRITAN)
     }
 }
 
@@ -222,6 +349,7 @@ pub fn simulate_tritan(c: sRGB) -> Oklab {
 }
 
 #[allow(non_snake_case)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Oklch {
     pub L: f32,
     pub C: f32,
@@ -232,8 +360,8 @@ impl From<Oklab> for Oklch {
     fn from(c: Oklab) -> Self {
         Self {
             L: c.L,
-            C: (c.a.powi(2) + c.b.powi(2)).sqrt(),
-            h: c.b.atan2(c.a),
+            C: sqrt(c.a * c.a + c.b * c.b),
+            h: atan2(c.b, c.a),
         }
     }
 }
@@ -247,21 +375,21 @@ impl From<sRGB> for Oklch {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use approx::assert_abs_diff_eq;
+    use approx::{assert_abs_diff_eq, assert_relative_eq};
     use itertools::iproduct;
 
     // Source: https://git.apcacontrast.com/documentation/README
     // Accessed 2023-03-19.
     #[test]
     fn test_apca() {
-        let c_888: sRGB = [0x88, 0x88, 0x88];
-        let c_fff: sRGB = [0xff, 0xff, 0xff];
-        let c_000: sRGB = [0x00, 0x00, 0x00];
-        let c_aaa: sRGB = [0xaa, 0xaa, 0xaa];
-        let c_123: sRGB = [0x11, 0x22, 0x33];
-        let c_def: sRGB = [0xdd, 0xee, 0xff];
-        let c_444: sRGB = [0x44, 0x44, 0x44];
-        let c_234: sRGB = [0x22, 0x33, 0x44];
+        let c_888 = sRGB([0x88, 0x88, 0x88]);
+        let c_fff = sRGB([0xff, 0xff, 0xff]);
+        let c_000 = sRGB([0x00, 0x00, 0x00]);
+        let c_aaa = sRGB([0xaa, 0xaa, 0xaa]);
+        let c_123 = sRGB([0x11, 0x22, 0x33]);
+        let c_def = sRGB([0xdd, 0xee, 0xff]);
+        let c_444 = sRGB([0x44, 0x44, 0x44]);
+        let c_234 = sRGB([0x22, 0x33, 0x44]);
 
         // This epsilon is exactly correct, since the result never exceeds 2^7 and f32 has 24 mantissa bits.
         let eps = 2.0_f32.powi(-17);
@@ -283,10 +411,103 @@ mod tests {
         assert_abs_diff_eq!(1.6349191031377903, APCA(&c_234, &c_123), epsilon = eps);
     }
 
+    #[test]
+    fn test_oklab_roundtrip() {
+        for (r, g, b) in iproduct!(
+            (0x00..=0xFF).step_by(5),
+            0x00..=0xFF,
+            (0x00..=0xFF).step_by(3)
+        ) {
+            let c = sRGB([r, g, b]);
+            let c_oklab = Oklab::from(c);
+            assert!(c_oklab.in_gamut());
+            assert_eq!(c_oklab.to_sRGB(), c);
+            assert_abs_diff_eq!(
+                APCA(&c, &sRGB([0xFF; 3])),
+                APCA_Oklab(&c_oklab, &Oklab::from(sRGB([0xFF; 3]))),
+                epsilon = 1e-2
+            );
+        }
+        assert!(!Oklab {
+            L: 50.0,
+            a: 40.0,
+            b: 0.0
+        }
+        .in_gamut());
+    }
+
+    #[test]
+    fn test_exact_srgb8() {
+        for c in 0..=255 {
+            let x = fast_srgb8::srgb8_to_f32(c);
+            // The table is correctly rounded, while `powf` can be a few ulps off.
+            assert_relative_eq!(exact_srgb8_to_f32(c), x, max_relative = 4.0 * f32::EPSILON);
+            assert_eq!(exact_f32_to_srgb8(x), c);
+            // A quarter of the way to the neighboring codes still rounds to `c`.
+            for neighbor in [c.saturating_sub(1), c.saturating_add(1)] {
+                let y = 0.75 * x + 0.25 * fast_srgb8::srgb8_to_f32(neighbor);
+                assert_eq!(exact_f32_to_srgb8(y), fast_srgb8::f32_to_srgb8(y));
+            }
+        }
+    }
+
+    // Sums and a hash over a grid of colors, recorded with `std`. Run this with
+    // `--no-default-features --features libm,fast-srgb8` to check that libm gives the same results.
+    // Exact sRGB conversions move a few simulated colors by one code, so they have their own values.
+    #[test]
+    fn test_backend_outputs() {
+        #[cfg(feature = "fast-srgb8")]
+        let (expected_hyab, expected_codes) = (2091429.140, 2573133586508855192);
+        #[cfg(not(feature = "fast-srgb8"))]
+        let (expected_hyab, expected_codes) = (2091429.166, 6284520878024530742);
+
+        let colors = iproduct!(
+            (0..=255).step_by(51),
+            (0..=255).step_by(51),
+            (0..=255).step_by(51)
+        )
+        .map(|(r, g, b)| sRGB([r, g, b]))
+        .collect::<alloc::vec::Vec<_>>();
+        let (mut apca, mut hyab) = (0.0_f64, 0.0_f64);
+        for (c1, c2) in iproduct!(colors.iter(), colors.iter()) {
+            apca += APCA(c1, c2).abs() as f64;
+            hyab += HyAB(&Oklab::from(*c1), &Oklab::from(*c2)) as f64;
+        }
+        let mut codes = 0_u64;
+        for (c, model, kind, severity) in iproduct!(
+            colors.iter(),
+            [
+                CvdModel::Brettel1997,
+                CvdModel::Vienot1999,
+                CvdModel::Machado2009
+            ],
+            [Deficiency::Protan, Deficiency::Deutan, Deficiency::Tritan],
+            [0.5, 1.0]
+        ) {
+            let sim = simulate(model, kind, severity, *c).to_sRGB();
+            codes = codes.wrapping_mul(31).wrapping_add(as_index(&sim) as u64);
+        }
+        assert_abs_diff_eq!(apca, 1464366.64, epsilon = 1e-2);
+        assert_abs_diff_eq!(hyab, expected_hyab, epsilon = 1e-3);
+        assert_eq!(codes, expected_codes);
+    }
+
+    #[test]
+    fn test_rgba() {
+        let c = Rgba::new(sRGB([0xFF, 0x80, 0x00]), 0.6);
+        assert_eq!(c.over(&sRGB([0xFF, 0xFF, 0xFF])), sRGB([0xFF, 0xB3, 0x66]));
+        assert_eq!(c.over(&sRGB([0x00, 0x00, 0x00])), sRGB([0x99, 0x4D, 0x00]));
+        assert_eq!(Rgba::from(c.rgb).over(&sRGB([0x12, 0x34, 0x56])), c.rgb);
+        assert_eq!(
+            Rgba::new(c.rgb, 0.0).over(&sRGB([0x12, 0x34, 0x56])),
+            sRGB([0x12, 0x34, 0x56])
+        );
+    }
+
     #[test]
     fn test_lms_roundtrip() {
         for (r, g, b) in iproduct!(0x00..=0xFF, 0x00..=0xFF, 0x00..=0xFF) {
-            let c = [r, g, b];
+            let c = sRGB([r, g, b]);
             let c_rgb: RGB = c.into();
             let c_lms: LMS = c.into(); // This goes through RGB first
             let c_lms_rgb: RGB = c_lms.into();
@@ -298,4 +519,185 @@ mod tests {
             assert_abs_diff_eq!(&c_rgb.b, &c_lms_rgb.b, epsilon = eps);
         }
     }
+
+    // Source: https://bottosson.github.io/posts/oklab/#table-of-example-xyz-and-oklab-pairs
+    // Accessed 2026-10-19. The Oklab values are rounded to 3 decimals before scaling, and the table
+    // goes straight from XYZ, so going through linear sRGB adds up to another 0.05.
+    #[test]
+    fn test_oklab_reference() {
+        // XYZ (D65) to linear sRGB, from IEC 61966-2-1.
+        let rgb = |x: f32, y: f32, z: f32| RGB {
+            r: 3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+            g: -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+            b: 0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+        };
+        for ((x, y, z), (l, a, b)) in [
+            ((0.950, 1.000, 1.089), (1.000, 0.000, 0.000)),
+            ((1.000, 0.000, 0.000), (0.450, 1.236, -0.019)),
+            ((0.000, 1.000, 0.000), (0.922, -0.671, 0.263)),
+            ((0.000, 0.000, 1.000), (0.153, -1.415, -0.449)),
+        ] {
+            let c = Oklab::from(rgb(x, y, z));
+            let eps = 0.001 * OKLAB_SCALE;
+            assert_abs_diff_eq!(c.L, l * OKLAB_SCALE, epsilon = eps);
+            assert_abs_diff_eq!(c.a, a * OKLAB_SCALE, epsilon = eps);
+            assert_abs_diff_eq!(c.b, b * OKLAB_SCALE, epsilon = eps);
+        }
+    }
+
+    // From DaltonLens-Python's Simulator_Brettel1997 by scripts/cvd_reference.py, which records
+    // the version used. Channels may be off by one, since neither side computes in float64.
+    #[test]
+    fn test_cvd_reference() {
+        for (c, expected) in [
+            (
+                sRGB([0xFA, 0x35, 0xEC]),
+                [[0x00, 0x71, 0xED], [0x78, 0xA0, 0xE9], [0xED, 0x69, 0x7C]],
+            ),
+            (
+                sRGB([0x3B, 0xDB, 0x78]),
+                [[0xE6, 0xCE, 0x77], [0xCA, 0xB8, 0x7D], [0x6D, 0xCC, 0xEB]],
+            ),
+            (
+                sRGB([0xFF, 0x00, 0x00]),
+                [[0x6C, 0x5C, 0x0C], [0xA4, 0x8B, 0x00], [0xFF, 0x00, 0x4E]],
+            ),
+            (
+                sRGB([0x00, 0x80, 0xFF]),
+                [[0x00, 0x82, 0xFF], [0x00, 0x85, 0xFF], [0x00, 0x95, 0xBA]],
+            ),
+            (
+                sRGB([0xF7, 0xC6, 0x01]),
+                [[0xE9, 0xC8, 0x03], [0xED, 0xCB, 0x00], [0xFF, 0xB8, 0xC0]],
+            ),
+            (
+                sRGB([0x11, 0x22, 0x33]),
+                [[0x18, 0x21, 0x33], [0x17, 0x21, 0x33], [0x0D, 0x24, 0x2B]],
+            ),
+        ] {
+            let simulated = [simulate_protan(c), simulate_deutan(c), simulate_tritan(c)];
+            for (sim, expected) in simulated.iter().zip(expected) {
+                let sim = sim.to_sRGB();
+                for k in 0..3 {
+                    assert!(
+                        sim[k].abs_diff(expected[k]) <= 1,
+                        "{} simulated as {}, expected {}",
+                        c,
+                        sim,
+                        sRGB(expected)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gray_axis() {
+        let mut last_l = -1.0;
+        for v in 0x00..=0xFF {
+            let gray = sRGB([v; 3]);
+            let c = Oklab::from(gray);
+            assert_abs_diff_eq!(c.a, 0.0, epsilon = 1e-3);
+            assert_abs_diff_eq!(c.b, 0.0, epsilon = 1e-3);
+            assert!(c.L > last_l);
+            last_l = c.L;
+            // Grays are on both planes of every simulation.
+            for sim in [
+                simulate_protan(gray),
+                simulate_deutan(gray),
+                simulate_tritan(gray),
+            ] {
+                assert_abs_diff_eq!(sim.L, c.L, epsilon = 1e-3);
+                assert_abs_diff_eq!(sim.a, 0.0, epsilon = 1e-3);
+                assert_abs_diff_eq!(sim.b, 0.0, epsilon = 1e-3);
+            }
+        }
+    }
+
+    fn cross(u: [f64; 3], v: [f64; 3]) -> [f64; 3] {
+        [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ]
+    }
+
+    fn dot(u: [f64; 3], v: [f64; 3]) -> f64 {
+        u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+    }
+
+    #[test]
+    fn test_simulation_planes() {
+        // Smith-Pokorny cone fundamentals and the anchor wavelengths, from build.rs.
+        // Everything here is recomputed from those inputs, independently of the derivation there.
+        let lms_from_xyz = |xyz: [f64; 3]| {
+            [
+                0.15514 * xyz[0] + 0.54312 * xyz[1] - 0.03286 * xyz[2],
+                -0.15514 * xyz[0] + 0.45684 * xyz[1] + 0.03286 * xyz[2],
+                0.01608 * xyz[2],
+            ]
+        };
+        // XYZ of linear sRGB white, from the row sums of XYZJuddVos_from_linearRGB_BT709.
+        let white = lms_from_xyz([
+            (40.9568 + 35.5041 + 17.9167) / 100.0,
+            (21.3389 + 70.6743 + 7.98680) / 100.0,
+            (1.86297 + 11.4620 + 91.2367) / 100.0,
+        ]);
+        let lms_475 = lms_from_xyz([0.13287, 0.11284, 0.9422]);
+        let lms_575 = lms_from_xyz([0.84394, 0.91558, 0.00197]);
+        let lms_485 = lms_from_xyz([0.05699, 0.16987, 0.5864]);
+        let lms_660 = lms_from_xyz([0.16161, 0.061, 0.00001]);
+
+        let deficiencies = [
+            (0, lms_475, lms_575, LMS::simulate_protan as fn(LMS) -> LMS),
+            (1, lms_475, lms_575, LMS::simulate_deutan),
+            (2, lms_485, lms_660, LMS::simulate_tritan),
+        ];
+        for (k, wing1, wing2, simulate) in deficiencies {
+            let mut axis = [0.0; 3];
+            axis[k] = 1.0;
+            let mut separation = cross(white, axis);
+            if dot(separation, wing1) < 0.0 {
+                separation = separation.map(|x| -x);
+            }
+            let planes = [cross(white, wing1), cross(white, wing2)];
+
+            for (r, g, b) in iproduct!(
+                (0x00..=0xFF).step_by(3),
+                (0x00..=0xFF).step_by(3),
+                (0x00..=0xFF).step_by(3)
+            ) {
+                let c = LMS::from(sRGB([r, g, b]));
+                let lms = [c.l, c.m, c.s].map(f64::from);
+                let sim = simulate(c);
+                let sim_lms = [sim.l, sim.m, sim.s].map(f64::from);
+
+                // Only the missing cone changes.
+                for j in (0..3).filter(|j| *j != k) {
+                    assert_eq!(sim_lms[j], lms[j]);
+                }
+                // On the plane for its side of the separation plane. Colors close to the
+                // separation plane may pick either, since the f32 test can round the other way.
+                let side = dot(separation, lms);
+                let norm = dot(lms, lms).sqrt();
+                let on_plane = |n: [f64; 3]| {
+                    dot(n, sim_lms).abs() <= 1e-5 * dot(n, n).sqrt() * dot(sim_lms, sim_lms).sqrt()
+                };
+                if side > 1e-6 * norm {
+                    assert!(on_plane(planes[0]), "{:?} {}", [r, g, b], k);
+                } else if side < -1e-6 * norm {
+                    assert!(on_plane(planes[1]), "{:?} {}", [r, g, b], k);
+                } else {
+                    assert!(on_plane(planes[0]) || on_plane(planes[1]));
+                }
+                // Projecting again changes nothing.
+                let again = simulate(LMS {
+                    l: sim.l,
+                    m: sim.m,
+                    s: sim.s,
+                });
+                assert_eq!([again.l, again.m, again.s], [sim.l, sim.m, sim.s]);
+            }
+        }
+    }
 }
@@ -0,0 +1,51 @@
+// Float functions that `core` doesn't have. With `std` these are the usual methods,
+// otherwise they come from libm.
+#[cfg(feature = "std")]
+mod imp {
+    pub fn powf(x: f32, y: f32) -> f32 {
+        x.powf(y)
+    }
+
+    pub fn cbrt(x: f32) -> f32 {
+        x.cbrt()
+    }
+
+    pub fn sqrt(x: f32) -> f32 {
+        x.sqrt()
+    }
+
+    pub fn atan2(y: f32, x: f32) -> f32 {
+        y.atan2(x)
+    }
+
+    pub fn round(x: f32) -> f32 {
+        x.round()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod imp {
+    pub use libm::{atan2f as atan2, cbrtf as cbrt, powf, roundf as round, sqrtf as sqrt};
+}
+
+pub use imp::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // libm and the platform's math library can differ in the last bit, no more.
+    #[test]
+    fn test_libm() {
+        let close = |x: f32, y: f32| (x - y).abs() <= 2.0 * f32::EPSILON * x.abs().max(1.0);
+        for i in 0..=1000 {
+            let x = i as f32 / 1000.0;
+            assert!(close(powf(x, 2.4), libm::powf(x, 2.4)));
+            assert!(close(powf(x, 1.0 / 2.4), libm::powf(x, 1.0 / 2.4)));
+            assert!(close(cbrt(x - 0.5), libm::cbrtf(x - 0.5)));
+            assert!(close(sqrt(x), libm::sqrtf(x)));
+            assert!(close(atan2(x - 0.5, 0.3), libm::atan2f(x - 0.5, 0.3)));
+            assert_eq!(round(x * 255.0), libm::roundf(x * 255.0));
+        }
+    }
+}
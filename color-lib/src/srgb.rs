@@ -0,0 +1,247 @@
+use crate::css::CSS_NAMES;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::ops::{Index, IndexMut};
+use core::str::FromStr;
+
+// An 8-bit sRGB color, as red, green and blue.
+// Formats as "#RRGGBB", and parses from any CSS color syntax without alpha: see `FromStr`.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct sRGB(pub [u8; 3]);
+
+pub fn as_index(c: &sRGB) -> usize {
+    // RGB order. Might change later.
+    let mut out: usize = c[2] as usize;
+    out |= (c[1] as usize) << 8;
+    out |= (c[0] as usize) << 16;
+    out
+}
+
+impl Index<usize> for sRGB {
+    type Output = u8;
+
+    fn index(&self, k: usize) -> &u8 {
+        &self.0[k]
+    }
+}
+
+impl IndexMut<usize> for sRGB {
+    fn index_mut(&mut self, k: usize) -> &mut u8 {
+        &mut self.0[k]
+    }
+}
+
+impl From<[u8; 3]> for sRGB {
+    fn from(c: [u8; 3]) -> Self {
+        Self(c)
+    }
+}
+
+impl From<sRGB> for [u8; 3] {
+    fn from(c: sRGB) -> Self {
+        c.0
+    }
+}
+
+// From 0xRRGGBB, ignoring the highest byte.
+impl From<u32> for sRGB {
+    fn from(x: u32) -> Self {
+        Self([(x >> 16) as u8, (x >> 8) as u8, x as u8])
+    }
+}
+
+impl From<sRGB> for u32 {
+    fn from(c: sRGB) -> Self {
+        as_index(&c) as u32
+    }
+}
+
+// "{:x}" gives "fa35ec", and "{:#x}" gives "#fa35ec".
+impl fmt::LowerHex for sRGB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("#")?;
+        }
+        write!(f, "{:06x}", as_index(self))
+    }
+}
+
+impl fmt::UpperHex for sRGB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("#")?;
+        }
+        write!(f, "{:06X}", as_index(self))
+    }
+}
+
+impl fmt::Display for sRGB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#X}", self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseColorError {
+    InvalidHex(String),
+    InvalidRgb(String),
+    UnknownName(String),
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseColorError::InvalidHex(s) => write!(f, "Invalid hex color {:?}", s),
+            ParseColorError::InvalidRgb(s) => write!(f, "Invalid rgb() color {:?}", s),
+            ParseColorError::UnknownName(s) => write!(f, "Unknown color name {:?}", s),
+        }
+    }
+}
+
+impl core::error::Error for ParseColorError {}
+
+// "RRGGBB" or "RGB", where each digit of "RGB" is doubled.
+fn parse_hex(digits: &str) -> Option<sRGB> {
+    if !digits.bytes().all(|d| d.is_ascii_hexdigit()) {
+        return None;
+    }
+    let x = u32::from_str_radix(digits, 16).ok()?;
+    match digits.len() {
+        6 => Some(x.into()),
+        3 => Some(sRGB([x >> 8, x >> 4, x].map(|d| (d & 0xF) as u8 * 0x11))),
+        _ => None,
+    }
+}
+
+// Integer channels from 0 to 255, separated by commas or spaces.
+fn parse_rgb(args: &str) -> Option<sRGB> {
+    let mut channels = args
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<u8>().ok());
+    let c = [channels.next()??, channels.next()??, channels.next()??];
+    match channels.next() {
+        None => Some(sRGB(c)),
+        Some(_) => None,
+    }
+}
+
+// Accepts, case-insensitively, "#RRGGBB", "#RGB", either without the "#",
+// "rgb(R, G, B)" or "rgb(R G B)", and the CSS color names.
+impl FromStr for sRGB {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        if let Some(digits) = lower.strip_prefix('#') {
+            parse_hex(digits).ok_or_else(|| ParseColorError::InvalidHex(s.to_string()))
+        } else if let Some(args) = lower.strip_prefix("rgb(") {
+            args.strip_suffix(')')
+                .and_then(parse_rgb)
+                .ok_or_else(|| ParseColorError::InvalidRgb(s.to_string()))
+        } else if let Ok(i) = CSS_NAMES.binary_search_by_key(&lower.as_str(), |(name, _)| name) {
+            Ok(CSS_NAMES[i].1.into())
+        } else {
+            parse_hex(&lower).ok_or_else(|| ParseColorError::UnknownName(s.to_string()))
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for sRGB {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for sRGB {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let c = sRGB([0xFA, 0x35, 0xEC]);
+        for s in [
+            "#FA35EC",
+            "fa35ec",
+            " #fA35eC ",
+            "rgb(250, 53, 236)",
+            "RGB(250 53 236)",
+        ] {
+            assert_eq!(s.parse::<sRGB>(), Ok(c));
+        }
+        assert_eq!("#F3E".parse(), Ok(sRGB([0xFF, 0x33, 0xEE])));
+        assert_eq!("RebeccaPurple".parse(), Ok(sRGB([0x66, 0x33, 0x99])));
+        assert_eq!("aliceblue".parse(), Ok(sRGB([0xF0, 0xF8, 0xFF])));
+        assert_eq!("yellowgreen".parse(), Ok(sRGB([0x9A, 0xCD, 0x32])));
+        assert!(CSS_NAMES.windows(2).all(|w| w[0].0 < w[1].0));
+
+        assert!(matches!(
+            "#FA35E".parse::<sRGB>(),
+            Err(ParseColorError::InvalidHex(_))
+        ));
+        assert!(matches!(
+            "#FA35EG".parse::<sRGB>(),
+            Err(ParseColorError::InvalidHex(_))
+        ));
+        assert!(matches!(
+            "rgb(256, 0, 0)".parse::<sRGB>(),
+            Err(ParseColorError::InvalidRgb(_))
+        ));
+        assert!(matches!(
+            "rgb(1, 2, 3, 4)".parse::<sRGB>(),
+            Err(ParseColorError::InvalidRgb(_))
+        ));
+        assert!(matches!(
+            "rgb(1, 2".parse::<sRGB>(),
+            Err(ParseColorError::InvalidRgb(_))
+        ));
+        assert!(matches!(
+            "blurple".parse::<sRGB>(),
+            Err(ParseColorError::UnknownName(_))
+        ));
+
+        assert_eq!(c.to_string(), "#FA35EC");
+        assert_eq!(format!("{:x} {:#x} {:X}", c, c, c), "fa35ec #fa35ec FA35EC");
+        assert_eq!(sRGB::from(0x000A10).to_string(), "#000A10");
+        assert_eq!(u32::from(c), 0xFA35EC);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use crate::Oklab;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Config {
+            background: sRGB,
+            palette: Vec<sRGB>,
+            oklab: Oklab,
+        }
+
+        let config = Config {
+            background: sRGB([0xFF, 0xFF, 0xFF]),
+            palette: vec![sRGB([0xFA, 0x35, 0xEC]), sRGB([0x00, 0x0A, 0x10])],
+            oklab: Oklab::from(sRGB([0xFA, 0x35, 0xEC])),
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.starts_with(
+            r##"{"background":"#FFFFFF","palette":["#FA35EC","#000A10"],"oklab":{"L":"##
+        ));
+        assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+
+        let lowercase = json.replace("#FA35EC", "fa35ec");
+        assert_eq!(serde_json::from_str::<Config>(&lowercase).unwrap(), config);
+        assert!(serde_json::from_str::<Config>(&json.replace("#FA35EC", "#FA35E")).is_err());
+    }
+}
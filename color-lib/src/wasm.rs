@@ -0,0 +1,119 @@
+// JavaScript bindings, for using the metrics in a web page. Build with
+// `wasm-pack build color-lib --features wasm`.
+// Colors are passed as CSS color strings like "#FA35EC" or "tomato" and returned as hex,
+// and Oklab and Oklch colors as [L, a, b] arrays.
+use crate::{sRGB, CvdModel, Deficiency, HyAB, Oklab, Oklch, ParseColorError, APCA};
+use alloc::vec::Vec;
+use wasm_bindgen::prelude::*;
+
+fn parse(hex: &str) -> Result<sRGB, JsValue> {
+    hex.parse()
+        .map_err(|e: ParseColorError| JsValue::from_str(&e.to_string()))
+}
+
+// `model` is "brettel", "vienot" or "machado", and `kind` is "protan", "deutan", "tritan" or "none".
+// `severity` goes from 0 for normal vision to 1 for dichromacy.
+fn simulation(model: &str, kind: &str, severity: f32) -> Result<impl Fn(&Oklab) -> Oklab, JsValue> {
+    let model: CvdModel = model.parse().map_err(|e: String| JsValue::from_str(&e))?;
+    let kind: Option<Deficiency> = match kind {
+        "none" => None,
+        _ => Some(kind.parse().map_err(|e: String| JsValue::from_str(&e))?),
+    };
+    if !(0.0..=1.0).contains(&severity) {
+        return Err(JsValue::from_str("Severity must be between 0 and 1"));
+    }
+    Ok(move |c: &Oklab| match kind {
+        Some(kind) => c.simulate(model, kind, severity),
+        None => *c,
+    })
+}
+
+#[wasm_bindgen]
+pub fn oklab(hex: &str) -> Result<Vec<f32>, JsValue> {
+    let c = Oklab::from(parse(hex)?);
+    Ok(alloc::vec![c.L, c.a, c.b])
+}
+
+#[wasm_bindgen]
+pub fn oklch(hex: &str) -> Result<Vec<f32>, JsValue> {
+    let c = Oklch::from(parse(hex)?);
+    Ok(alloc::vec![c.L, c.C, c.h])
+}
+
+// The closest hex color to an Oklab color.
+#[wasm_bindgen]
+pub fn from_oklab(lab: &[f32]) -> Result<String, JsValue> {
+    let [l, a, b] = lab else {
+        return Err(JsValue::from_str(&alloc::format!(
+            "Expected [L, a, b], got {} values",
+            lab.len()
+        )));
+    };
+    let c = Oklab {
+        L: *l,
+        a: *a,
+        b: *b,
+    };
+    Ok(c.to_sRGB().to_string())
+}
+
+#[wasm_bindgen]
+pub fn hyab(hex1: &str, hex2: &str) -> Result<f32, JsValue> {
+    Ok(HyAB(&parse(hex1)?.into(), &parse(hex2)?.into()))
+}
+
+#[wasm_bindgen]
+pub fn apca(text: &str, bg: &str) -> Result<f32, JsValue> {
+    Ok(APCA(&parse(text)?, &parse(bg)?))
+}
+
+// How `hex` looks with color vision deficiency `kind`, as simulated by `model`.
+#[wasm_bindgen]
+pub fn simulate(model: &str, kind: &str, severity: f32, hex: &str) -> Result<String, JsValue> {
+    let c = simulation(model, kind, severity)?(&parse(hex)?.into());
+    Ok(c.to_sRGB().to_string())
+}
+
+// HyAB distance between two colors, as seen with color vision deficiency `kind`.
+#[wasm_bindgen]
+pub fn simulated_hyab(
+    model: &str,
+    kind: &str,
+    severity: f32,
+    hex1: &str,
+    hex2: &str,
+) -> Result<f32, JsValue> {
+    let f = simulation(model, kind, severity)?;
+    Ok(HyAB(&f(&parse(hex1)?.into()), &f(&parse(hex2)?.into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only the successful calls, since errors need a JavaScript host.
+    #[test]
+    fn test_bindings() {
+        let (c1, c2) = (sRGB([0xFA, 0x35, 0xEC]), sRGB([0x3B, 0xDB, 0x78]));
+        let (hex1, hex2) = ("#FA35EC", "#3bdb78");
+        let lab = Oklab::from(c1);
+        assert_eq!(oklab(hex1).unwrap(), [lab.L, lab.a, lab.b]);
+        assert_eq!(oklch(hex1).unwrap()[1], Oklch::from(c1).C);
+        assert_eq!(from_oklab(&[lab.L, lab.a, lab.b]).unwrap(), hex1);
+        assert_eq!(hyab(hex1, hex2).unwrap(), HyAB(&lab, &c2.into()));
+        assert_eq!(apca(hex1, hex2).unwrap(), APCA(&c1, &c2));
+        assert_eq!(simulate("brettel", "none", 1.0, hex1).unwrap(), hex1);
+        assert_eq!(
+            simulate("brettel", "deutan", 1.0, hex1).unwrap(),
+            lab.simulate_deutan().to_sRGB().to_string()
+        );
+        let (model, kind) = (CvdModel::Machado2009, Deficiency::Protan);
+        assert_eq!(
+            simulated_hyab("machado", "protan", 0.6, hex1, hex2).unwrap(),
+            HyAB(
+                &lab.simulate(model, kind, 0.6),
+                &Oklab::from(c2).simulate(model, kind, 0.6)
+            )
+        );
+    }
+}
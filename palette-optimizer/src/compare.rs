@@ -0,0 +1,150 @@
+use color_lib::{sRGB, Gamut, HyAB, Oklab, RgbColor};
+use itertools::Itertools;
+
+// The best way to pair up the colors of two palettes.
+#[derive(Clone, Debug)]
+pub struct PaletteMatch {
+    // Color i of the first palette is paired with color `assignment[i]` of the second.
+    pub assignment: Vec<usize>,
+    pub total: f32,
+    // The largest distance between paired colors.
+    pub max: f32,
+}
+
+// Hungarian algorithm for the square `cost` matrix, returning the column assigned to each row.
+// Potentials are 1-indexed, with index 0 standing for "unassigned".
+fn assign(cost: &Vec<Vec<f32>>) -> Vec<usize> {
+    let n = cost.len();
+    let mut u = vec![0.0_f32; n + 1];
+    let mut v = vec![0.0_f32; n + 1];
+    let mut p = vec![0; n + 1];
+    let mut way = vec![0; n + 1];
+    for i in 1..=n {
+        p[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![f32::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = f32::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=n {
+                if !used[j] {
+                    let cur = cost[i0 - 1][j - 1] - u[i0] - v[j];
+                    if cur < minv[j] {
+                        minv[j] = cur;
+                        way[j] = j0;
+                    }
+                    if minv[j] < delta {
+                        delta = minv[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for j in 1..=n {
+        assignment[p[j] - 1] = j - 1;
+    }
+    assignment
+}
+
+// Pairs up the colors of `a` and `b` to minimize the total HyAB distance, so palettes that only differ in order match exactly.
+// Both palettes are 8-bit codes in `gamut`.
+pub fn match_palettes(a: &Vec<sRGB>, b: &Vec<sRGB>, gamut: Gamut) -> PaletteMatch {
+    assert_eq!(a.len(), b.len(), "Palettes have different sizes");
+    let decode = |c: &sRGB| Oklab::from(RgbColor::from_8bit(gamut, *c));
+    let b_oklab = b.iter().map(decode).collect_vec();
+    let cost = a
+        .iter()
+        .map(|c1| {
+            let c1 = decode(c1);
+            b_oklab.iter().map(|c2| HyAB(&c1, c2)).collect_vec()
+        })
+        .collect_vec();
+    let assignment = assign(&cost);
+    let distances = assignment
+        .iter()
+        .enumerate()
+        .map(|(i, j)| cost[i][*j])
+        .collect_vec();
+    PaletteMatch {
+        assignment: assignment,
+        total: distances.iter().sum(),
+        max: distances.iter().copied().fold(0.0, f32::max),
+    }
+}
+
+// Groups palettes that match within `tolerance`, meaning no paired colors are further apart than that.
+// Each group is a list of indices into `palettes`, compared against its first member.
+pub fn group_similar(palettes: &[Vec<sRGB>], gamut: Gamut, tolerance: f32) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for (i, palette) in palettes.iter().enumerate() {
+        match groups
+            .iter_mut()
+            .find(|g| match_palettes(&palettes[g[0]], palette, gamut).max <= tolerance)
+        {
+            Some(group) => group.push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_palettes() {
+        let a = vec![
+            sRGB([0xFA, 0x35, 0xEC]),
+            sRGB([0x94, 0x49, 0xFF]),
+            sRGB([0x3B, 0xDB, 0x78]),
+            sRGB([0xBF, 0x18, 0xBE]),
+        ];
+        let b = vec![a[2], a[0], a[3], a[1]];
+        let m = match_palettes(&a, &b, Gamut::Srgb);
+        assert_eq!(m.assignment, vec![1, 3, 0, 2]);
+        assert_eq!(m.total, 0.0);
+
+        let mut c = b.clone();
+        c[0][1] += 2;
+        let m = match_palettes(&a, &c, Gamut::Srgb);
+        assert_eq!(m.assignment, vec![1, 3, 0, 2]);
+        assert!(m.max > 0.0 && m.max < 2.0);
+
+        let d = vec![sRGB([0x00, 0x00, 0x00]); 4];
+        assert_eq!(
+            group_similar(&[a.clone(), d, b, c.clone()], Gamut::Srgb, 2.0),
+            vec![vec![0, 2, 3], vec![1]]
+        );
+
+        // The same codes are further apart in a wider gamut.
+        let srgb = match_palettes(&a, &c, Gamut::Srgb).max;
+        assert!(match_palettes(&a, &c, Gamut::Rec2020).max > srgb);
+    }
+}
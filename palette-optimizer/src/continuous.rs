@@ -0,0 +1,400 @@
+use crate::optimizer::{Control, Metric, Observer, Schedule, Snapshot, TIME_CHECK_INTERVAL};
+use crate::score::PairImportance;
+use color_lib::{sRGB, APCA_Oklab, CvdModel, Deficiency, Gamut, HyAB, Oklab, RgbColor};
+use itertools::Itertools;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::Instant;
+
+// Colors outside the target gamut lose this much score per unit of RGB they are out by,
+// which pushes the simplex back inside instead of leaving it stuck on the boundary.
+const GAMUT_PENALTY: f32 = 1000.0;
+// Size of each new simplex, in Oklab units.
+const STEP: f32 = 5.0;
+// Once the simplex is smaller than this, it has converged and is restarted around the best point.
+const MIN_SIZE: f32 = 0.01;
+
+// Normal vision, then each deficiency, in the order of the pair metrics.
+const SIMULATIONS: usize = 4;
+
+fn simulations(c: &Oklab, cvd_model: CvdModel, severity: f32) -> [Oklab; SIMULATIONS] {
+    [
+        *c,
+        c.simulate(cvd_model, Deficiency::Protan, severity),
+        c.simulate(cvd_model, Deficiency::Deutan, severity),
+        c.simulate(cvd_model, Deficiency::Tritan, severity),
+    ]
+}
+
+fn gamut_excess(c: &Oklab, gamut: Gamut) -> f32 {
+    c.to_rgb_color(gamut)
+        .rgb
+        .iter()
+        .map(|x| (-x).max(x - 1.0).max(0.0))
+        .sum()
+}
+
+// Maximizes the worst weighted score with Nelder-Mead on the Oklab coordinates of the colors,
+// computing every metric exactly instead of from lookup tables. Colors are only rounded to
+// 8 bits at the end, so the final palette can score slightly differently than the optimizer saw.
+// Scores the same metrics as a categorical `Optimizer`: the four pair distances, then APCA.
+pub struct ContinuousOptimizer {
+    // Fixed colors, in each of the simulated spaces.
+    fixed: Vec<[Oklab; SIMULATIONS]>,
+    fixed_srgb: Vec<sRGB>,
+    importance: PairImportance,
+    weights: [f32; 5],
+    backgrounds: Vec<Oklab>,
+    gamut: Gamut,
+    cvd_model: CvdModel,
+    severity: f32,
+    best: (f32, Metric, Vec<f32>),
+    rng: StdRng,
+}
+
+impl ContinuousOptimizer {
+    // Only colors from index `fixed` onwards are optimized.
+    // Colors are 8-bit codes in `gamut`, and are kept inside it.
+    // Deficiencies are simulated with `cvd_model` at `severity`, like `Luts::for_cvd`.
+    pub fn new(
+        colors: Vec<sRGB>,
+        fixed: usize,
+        importance: PairImportance,
+        weights: [f32; 5],
+        gamut: Gamut,
+        cvd_model: CvdModel,
+        severity: f32,
+    ) -> Self {
+        let decode = |c: &sRGB| Oklab::from(RgbColor::from_8bit(gamut, *c));
+        assert!(fixed < colors.len(), "Nothing to optimize");
+        let x = colors[fixed..]
+            .iter()
+            .flat_map(|c| {
+                let c = decode(c);
+                [c.L, c.a, c.b]
+            })
+            .collect_vec();
+        let mut optimizer = Self {
+            fixed: colors[..fixed]
+                .iter()
+                .map(|c| simulations(&decode(c), cvd_model, severity))
+                .collect(),
+            fixed_srgb: colors[..fixed].to_vec(),
+            importance: importance,
+            weights: weights,
+            backgrounds: vec![
+                sRGB([0x00, 0x00, 0x00]).into(),
+                sRGB([0xFF, 0xFF, 0xFF]).into(),
+            ],
+            gamut: gamut,
+            cvd_model: cvd_model,
+            severity: severity,
+            best: (f32::NEG_INFINITY, Metric::Const(0, fixed), vec![]),
+            rng: StdRng::from_entropy(),
+        };
+        optimizer.evaluate(&x);
+        optimizer
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    // Worst weighted score at `x`, minus the gamut penalty, and where it comes from.
+    fn score(&self, x: &[f32]) -> (f32, Metric) {
+        let fixed = self.fixed.len();
+        let colors = x
+            .chunks(3)
+            .map(|p| Oklab {
+                L: p[0],
+                a: p[1],
+                b: p[2],
+            })
+            .collect_vec();
+        let penalty = colors
+            .iter()
+            .map(|c| gamut_excess(c, self.gamut))
+            .sum::<f32>()
+            * GAMUT_PENALTY;
+        let simulated = colors
+            .iter()
+            .map(|c| simulations(c, self.cvd_model, self.severity))
+            .collect_vec();
+        let all = self.fixed.iter().chain(&simulated).collect_vec();
+
+        let mut min = (f32::INFINITY, Metric::Const(0, fixed));
+        for (k, w) in self.weights[..SIMULATIONS].iter().enumerate() {
+            for j in fixed..all.len() {
+                for i in 0..j {
+                    let dist = HyAB(&all[i][k], &all[j][k]);
+                    let s = self.importance.pair_score(i, j, dist) / w;
+                    if s < min.0 {
+                        min = (s, Metric::Pair(k, (i, j)));
+                    }
+                }
+            }
+        }
+        for (j, c) in colors.iter().enumerate() {
+            let s = self
+                .backgrounds
+                .iter()
+                .map(|bg| APCA_Oklab(c, bg))
+                .fold(f32::INFINITY, f32::min)
+                / self.weights[SIMULATIONS];
+            if s < min.0 {
+                min = (s, Metric::Const(0, fixed + j));
+            }
+        }
+        (min.0 - penalty, min.1)
+    }
+
+    // Nelder-Mead minimizes, so this returns the negated score.
+    fn evaluate(&mut self, x: &[f32]) -> f32 {
+        let (score, metric) = self.score(x);
+        if score > self.best.0 || self.best.2.is_empty() {
+            self.best = (score, metric, x.to_vec());
+        }
+        -score
+    }
+
+    fn new_simplex(&mut self, x0: &[f32]) -> Vec<(f32, Vec<f32>)> {
+        let mut simplex = vec![(self.evaluate(x0), x0.to_vec())];
+        for i in 0..x0.len() {
+            let mut x = x0.to_vec();
+            // Random directions, so restarts don't retrace the same path.
+            x[i] += if self.rng.gen() { STEP } else { -STEP };
+            simplex.push((self.evaluate(&x), x));
+        }
+        simplex
+    }
+
+    // Runs Nelder-Mead with restarts, counting one iteration per evaluation.
+    // A step can take several evaluations, so the count is capped at `schedule.max_iter`.
+    // Uses the dimension-dependent coefficients of Gao and Han (2012), which hold up much better
+    // than the standard ones with several colors.
+    pub fn run(&mut self, schedule: &Schedule, observer: &mut dyn Observer) -> u64 {
+        let interval = observer.interval().max(1);
+        let start_time = Instant::now();
+        let n = self.best.2.len();
+        let dim = n as f32;
+        let (alpha, gamma, rho, sigma) = (
+            1.0,
+            1.0 + 2.0 / dim,
+            0.75 - 1.0 / (2.0 * dim),
+            1.0 - 1.0 / dim.max(2.0),
+        );
+        let along = |c: &Vec<f32>, x: &Vec<f32>, t: f32| {
+            c.iter()
+                .zip(x)
+                .map(|(ci, xi)| ci + t * (xi - ci))
+                .collect_vec()
+        };
+
+        if schedule.target_score.is_some_and(|t| self.best.0 >= t) {
+            return 0;
+        }
+        let best_x = self.best.2.clone();
+        let mut simplex = self.new_simplex(&best_x);
+        let mut it = n as u64 + 1;
+        let mut next_snapshot = interval;
+        let mut next_time_check = TIME_CHECK_INTERVAL;
+        let mut last_improvement = 0;
+        let mut best_score = self.best.0;
+        while it < schedule.max_iter {
+            simplex.sort_by(|(f1, _), (f2, _)| f1.partial_cmp(f2).unwrap());
+            let size = simplex[1..]
+                .iter()
+                .flat_map(|(_, x)| x.iter().zip(&simplex[0].1).map(|(a, b)| (a - b).abs()))
+                .fold(0.0, f32::max);
+            if size < MIN_SIZE {
+                let best_x = self.best.2.clone();
+                simplex = self.new_simplex(&best_x);
+                it += n as u64 + 1;
+                continue;
+            }
+
+            let worst = simplex[n].clone();
+            let mut centroid = vec![0.0; n];
+            for (_, x) in &simplex[..n] {
+                for (c, xi) in centroid.iter_mut().zip(x) {
+                    *c += xi / dim;
+                }
+            }
+            let reflected = along(&centroid, &worst.1, -alpha);
+            let f_reflected = self.evaluate(&reflected);
+            it += 1;
+            if f_reflected < simplex[0].0 {
+                let expanded = along(&centroid, &reflected, gamma);
+                let f_expanded = self.evaluate(&expanded);
+                it += 1;
+                simplex[n] = if f_expanded < f_reflected {
+                    (f_expanded, expanded)
+                } else {
+                    (f_reflected, reflected)
+                };
+            } else if f_reflected < simplex[n - 1].0 {
+                simplex[n] = (f_reflected, reflected);
+            } else {
+                let (contracted, threshold) = if f_reflected < worst.0 {
+                    (along(&centroid, &reflected, rho), f_reflected)
+                } else {
+                    (along(&centroid, &worst.1, rho), worst.0)
+                };
+                let f_contracted = self.evaluate(&contracted);
+                it += 1;
+                if f_contracted < threshold {
+                    simplex[n] = (f_contracted, contracted);
+                } else {
+                    let x0 = simplex[0].1.clone();
+                    for vertex in simplex[1..].iter_mut() {
+                        let x = along(&x0, &vertex.1, sigma);
+                        *vertex = (self.evaluate(&x), x);
+                    }
+                    it += n as u64;
+                }
+            }
+
+            if self.best.0 > best_score {
+                best_score = self.best.0;
+                last_improvement = it;
+                if schedule.target_score.is_some_and(|t| best_score >= t) {
+                    break;
+                }
+            }
+            if schedule
+                .patience
+                .is_some_and(|p| it - last_improvement >= p)
+            {
+                break;
+            }
+            if it >= next_time_check {
+                next_time_check += TIME_CHECK_INTERVAL;
+                if schedule
+                    .time_limit
+                    .is_some_and(|t| start_time.elapsed() >= t)
+                {
+                    break;
+                }
+            }
+            if it >= next_snapshot {
+                next_snapshot += interval;
+                // The current colors are the simplex's best vertex.
+                let (_, x) = simplex
+                    .iter()
+                    .min_by(|(f1, _), (f2, _)| f1.partial_cmp(f2).unwrap())
+                    .unwrap();
+                let (score, limiting) = self.score(x);
+                let colors = self.colors(x);
+                let snapshot = Snapshot {
+                    iteration: it,
+                    colors: &colors,
+                    score: score,
+                    best_score: best_score,
+                    limiting_score: score,
+                    limiting: limiting,
+                };
+                if let Control::Stop = observer.observe(&snapshot) {
+                    break;
+                }
+            }
+        }
+        it.min(schedule.max_iter)
+    }
+
+    // The fixed colors, then the colors at `x` rounded to 8 bits.
+    fn colors(&self, x: &[f32]) -> Vec<sRGB> {
+        self.fixed_srgb
+            .iter()
+            .copied()
+            .chain(x.chunks(3).map(|p| {
+                Oklab {
+                    L: p[0],
+                    a: p[1],
+                    b: p[2],
+                }
+                .to_rgb_color(self.gamut)
+                .to_8bit()
+            }))
+            .collect_vec()
+    }
+
+    // Best score before rounding, and the best colors rounded to 8 bits.
+    pub fn get_best(&self) -> (f32, Vec<sRGB>) {
+        (self.best.0, self.colors(&self.best.2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_continuous() {
+        let colors = vec![
+            sRGB([0x00, 0x00, 0x00]),
+            sRGB([0xFF, 0xFF, 0xFF]),
+            sRGB([0x80, 0x80, 0x80]),
+            sRGB([0x81, 0x80, 0x80]),
+        ];
+        let mut optimizer = ContinuousOptimizer::new(
+            colors.clone(),
+            2,
+            PairImportance::uniform(4),
+            [1.0; 5],
+            Gamut::Srgb,
+            CvdModel::Brettel1997,
+            1.0,
+        )
+        .with_seed(0);
+        let initial = optimizer.get_best().0;
+        assert_eq!(optimizer.run(&Schedule::new(20000), &mut ()), 20000);
+        let (score, best) = optimizer.get_best();
+        assert!(score > initial);
+        assert!(best.starts_with(&colors[..2]));
+        assert!(best[2..]
+            .iter()
+            .all(|c| Oklab::from(*c).in_gamut() && *c != colors[2]));
+    }
+
+    struct CheckSnapshots {
+        count: usize,
+    }
+
+    impl Observer for CheckSnapshots {
+        fn interval(&self) -> u64 {
+            100
+        }
+
+        fn observe(&mut self, snapshot: &Snapshot) -> Control {
+            // The current vertex can be worse than the best seen so far.
+            assert!(snapshot.score <= snapshot.best_score);
+            assert_eq!(snapshot.limiting_score, snapshot.score);
+            assert_eq!(snapshot.colors.len(), 4);
+            self.count += 1;
+            Control::Continue
+        }
+    }
+
+    #[test]
+    fn test_continuous_snapshot() {
+        let colors = vec![
+            sRGB([0x00, 0x00, 0x00]),
+            sRGB([0xFF, 0xFF, 0xFF]),
+            sRGB([0x80, 0x80, 0x80]),
+            sRGB([0x81, 0x80, 0x80]),
+        ];
+        let mut optimizer = ContinuousOptimizer::new(
+            colors,
+            2,
+            PairImportance::uniform(4),
+            [1.0; 5],
+            Gamut::Srgb,
+            CvdModel::Brettel1997,
+            1.0,
+        )
+        .with_seed(0);
+        let mut observer = CheckSnapshots { count: 0 };
+        optimizer.run(&Schedule::new(2000), &mut observer);
+        assert!(observer.count > 0);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::lut::ColorLut;
+use crate::region::Region;
+use color_lib::{sRGB, HyAB, Oklab, Oklch};
+use itertools::Itertools;
+use rand::Rng;
+use serde::Serialize;
+use std::f32::consts::PI;
+
+// Number of random candidates the greedy strategies choose from.
+const CANDIDATES: usize = 4096;
+
+// How to pick the starting colors of a run.
+#[derive(Clone, Debug, Serialize)]
+pub enum Init {
+    Random,
+    // Each color is the candidate farthest from every color chosen so far.
+    FarthestPoint,
+    // Like `FarthestPoint`, but each color is drawn with probability proportional to its squared distance.
+    KMeansPlusPlus,
+    // Colors evenly spaced around the Oklch hue wheel, each as vivid as possible.
+    HueWheel,
+    // These colors first, then random ones. Unlike existing colors, these are optimized,
+    // so any outside the region are replaced by the closest of many random colors inside it.
+    Palette(Vec<sRGB>),
+}
+
+fn random_color(region: Option<&Region>, rng: &mut impl Rng) -> sRGB {
+    match region {
+        Some(r) => r.random_color(rng),
+        None => sRGB(rng.gen()),
+    }
+}
+
+// `c` if it is allowed, or else the closest of many random allowed colors, as seen in `space`.
+fn closest_sampled_in_region(
+    c: &sRGB,
+    space: &dyn ColorLut<Oklab>,
+    region: Option<&Region>,
+    rng: &mut impl Rng,
+) -> sRGB {
+    match region {
+        Some(r) if !r.contains(c) => {
+            let target = space.get(c);
+            (0..CANDIDATES)
+                .map(|_| r.random_color(rng))
+                .min_by(|c1, c2| {
+                    let (d1, d2) = (HyAB(&space.get(c1), &target), HyAB(&space.get(c2), &target));
+                    d1.partial_cmp(&d2).unwrap()
+                })
+                .unwrap()
+        }
+        _ => *c,
+    }
+}
+
+// The smallest distance between `c1` and `c2` in any of `spaces`.
+fn distance(spaces: &[&dyn ColorLut<Oklab>], c1: &sRGB, c2: &sRGB) -> f32 {
+    spaces
+        .iter()
+        .map(|lut| HyAB(&lut.get(c1), &lut.get(c2)))
+        .fold(f32::INFINITY, f32::min)
+}
+
+// Greedily picks `n` candidates, each chosen by `pick` from the candidates' distances to the colors so far.
+fn pick_greedy<R: Rng>(
+    existing: &[sRGB],
+    n: usize,
+    spaces: &[&dyn ColorLut<Oklab>],
+    region: Option<&Region>,
+    rng: &mut R,
+    pick: impl Fn(&Vec<f32>, &mut R) -> usize,
+) -> Vec<sRGB> {
+    let candidates = (0..CANDIDATES)
+        .map(|_| random_color(region, rng))
+        .collect_vec();
+    let mut distances = vec![f32::INFINITY; candidates.len()];
+    for c in existing {
+        for (d, candidate) in distances.iter_mut().zip(&candidates) {
+            *d = d.min(distance(spaces, c, candidate));
+        }
+    }
+
+    let mut colors = Vec::with_capacity(n);
+    for _ in 0..n {
+        let i = pick(&distances, rng);
+        let c = candidates[i];
+        for (d, candidate) in distances.iter_mut().zip(&candidates) {
+            *d = d.min(distance(spaces, &c, candidate));
+        }
+        colors.push(c);
+    }
+    colors
+}
+
+fn farthest(distances: &Vec<f32>, _rng: &mut impl Rng) -> usize {
+    // With nothing chosen yet, every distance is infinite and the first candidate is as good as any.
+    distances
+        .iter()
+        .position_max_by(|d1, d2| d1.partial_cmp(d2).unwrap())
+        .unwrap()
+}
+
+fn sample_squared(distances: &Vec<f32>, rng: &mut impl Rng) -> usize {
+    let weights = distances
+        .iter()
+        .map(|d| if d.is_finite() { d * d } else { 1.0 })
+        .collect_vec();
+    let mut r = rng.gen::<f32>() * weights.iter().sum::<f32>();
+    for (i, w) in weights.iter().enumerate() {
+        r -= w;
+        if r <= 0.0 {
+            return i;
+        }
+    }
+    weights.len() - 1
+}
+
+fn hue_wheel(
+    n: usize,
+    space: &dyn ColorLut<Oklab>,
+    region: Option<&Region>,
+    rng: &mut impl Rng,
+) -> Vec<sRGB> {
+    let candidates = (0..CANDIDATES)
+        .map(|_| {
+            let c = random_color(region, rng);
+            (c, Oklch::from(space.get(&c)))
+        })
+        .collect_vec();
+    let offset = rng.gen_range(0.0..2.0 * PI);
+    let half_sector = PI / n as f32;
+    (0..n)
+        .map(|k| {
+            let hue = offset + 2.0 * PI * k as f32 / n as f32;
+            candidates
+                .iter()
+                .filter(|(_, lch)| {
+                    // Distance around the wheel, between 0 and PI.
+                    let diff = (lch.h - hue).rem_euclid(2.0 * PI);
+                    diff.min(2.0 * PI - diff) <= half_sector
+                })
+                .max_by(|(_, lch1), (_, lch2)| lch1.C.partial_cmp(&lch2.C).unwrap())
+                .map(|(c, _)| *c)
+                .unwrap_or_else(|| random_color(region, rng))
+        })
+        .collect_vec()
+}
+
+// `n` colors to follow `existing`, with distances measured in every space of `spaces`.
+// The first space gives the hues for `Init::HueWheel`.
+pub fn initial_colors(
+    init: &Init,
+    existing: &[sRGB],
+    n: usize,
+    spaces: &[&dyn ColorLut<Oklab>],
+    region: Option<&Region>,
+    rng: &mut impl Rng,
+) -> Vec<sRGB> {
+    match init {
+        Init::Random => (0..n).map(|_| random_color(region, rng)).collect_vec(),
+        Init::FarthestPoint => pick_greedy(existing, n, spaces, region, rng, farthest),
+        Init::KMeansPlusPlus => pick_greedy(existing, n, spaces, region, rng, sample_squared),
+        Init::HueWheel => hue_wheel(n, spaces[0], region, rng),
+        Init::Palette(palette) => {
+            let mut colors = palette
+                .iter()
+                .take(n)
+                .map(|c| closest_sampled_in_region(c, spaces[0], region, rng))
+                .collect_vec();
+            while colors.len() < n {
+                colors.push(random_color(region, rng));
+            }
+            colors
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lut::SrgbLut;
+    use crate::region::OklchBounds;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_farthest_point() {
+        let lut = SrgbLut::new(Oklab::from);
+        let existing = [sRGB([0x00, 0x00, 0x00]), sRGB([0xFF, 0xFF, 0xFF])];
+        let spaces: [&dyn ColorLut<Oklab>; 1] = [&lut];
+        let min_distance = |colors: &Vec<sRGB>| {
+            existing
+                .iter()
+                .chain(colors)
+                .tuple_combinations()
+                .map(|(c1, c2)| distance(&spaces, c1, c2))
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let random = initial_colors(&Init::Random, &existing, 6, &spaces, None, &mut rng);
+        let farthest = initial_colors(&Init::FarthestPoint, &existing, 6, &spaces, None, &mut rng);
+        assert_eq!(farthest.len(), 6);
+        assert!(min_distance(&farthest) > min_distance(&random));
+
+        // Black is too dark for the region, so it moves to a dark, nearly gray sample.
+        // The samples are random, so this only checks that it's closer to black than the middle of
+        // the region, at L = 50, is.
+        let bounds = OklchBounds {
+            lightness: Some((40.0, 60.0)),
+            ..OklchBounds::default()
+        };
+        let region = Region::new(bounds);
+        let gray = sRGB([0x80, 0x80, 0x80]);
+        let init = Init::Palette(vec![sRGB([0x00, 0x00, 0x00]), gray]);
+        let colors = initial_colors(&init, &[], 3, &spaces, Some(&region), &mut rng);
+        assert!(colors.iter().all(|c| region.contains(c)));
+        assert_eq!(colors[1], gray);
+        let dark = Oklch::from(colors[0]);
+        // HyAB from black is L + C.
+        assert!(dark.L + dark.C < 50.0);
+    }
+}
@@ -0,0 +1,675 @@
+use crate::continuous::ContinuousOptimizer;
+use crate::init::{initial_colors, Init};
+use crate::lut::{make_lut, ColorLut, Lut, LutStorage};
+use crate::metric::{
+    composited_distance, AdjacentDistance, Constraint, PairDistance, PairMetric, Translucency,
+};
+use crate::optimizer::{Metric, Objective, Observer, Optimizer, Schedule};
+use crate::region::Region;
+use crate::score::PairImportance;
+use color_lib::*;
+use itertools::Itertools;
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+pub const PAIR_METRIC_NAMES: [&str; 4] = ["normal", "protan", "deutan", "tritan"];
+pub const CONSTRAINT_NAMES: [&str; 1] = ["apca"];
+// Pair metrics first, then constraints, in the order of the names above.
+pub const DEFAULT_WEIGHTS: [f32; 5] = [25.0, 20.0, 20.0, 15.0, 30.0];
+
+// Lookup tables for the standard metrics. These take a while to build, so share them between jobs.
+// Palettes are searched as 8-bit codes in `gamut`, so the tables decode every color in it first.
+// The protan, deutan and tritan tables simulate each deficiency with `cvd_model` at `severity`.
+pub struct Luts {
+    pub gamut: Gamut,
+    pub cvd_model: CvdModel,
+    pub severity: f32,
+    pub(crate) color: Lut<Oklab>,
+    pub(crate) prot: Lut<Oklab>,
+    pub(crate) deut: Lut<Oklab>,
+    pub(crate) trit: Lut<Oklab>,
+    pub(crate) apca_constraint: Lut<f32>,
+}
+
+impl Luts {
+    pub fn new() -> Self {
+        Self::with_storage([LutStorage::Dense; 5])
+    }
+
+    // How to store the table of each metric, in the order of PAIR_METRIC_NAMES, then CONSTRAINT_NAMES.
+    pub fn with_storage(storage: [LutStorage; 5]) -> Self {
+        Self::for_gamut(storage, Gamut::Srgb)
+    }
+
+    // Colors are 8-bit codes in `gamut`. The tables are indexed by 24 bits, so even palettes for 10-bit displays
+    // are searched at 8 bits.
+    pub fn for_gamut(storage: [LutStorage; 5], gamut: Gamut) -> Self {
+        Self::for_cvd(storage, gamut, CvdModel::Brettel1997, 1.0)
+    }
+
+    // Severity goes from 0 for normal vision to 1 for dichromacy, as in `color_lib::simulate`.
+    pub fn for_cvd(
+        storage: [LutStorage; 5],
+        gamut: Gamut,
+        cvd_model: CvdModel,
+        severity: f32,
+    ) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&severity),
+            "Severity must be between 0 and 1"
+        );
+        if gamut != Gamut::Srgb {
+            return Self::wide_gamut(storage, gamut, cvd_model, severity);
+        }
+        let bgs = [sRGB([0x00, 0x00, 0x00]), sRGB([0xFF, 0xFF, 0xFF])];
+        // let backgrounds = bgs.iter().map(|c| (*c).into()).collect_vec();
+        // let constraint_lut =
+        //     SrgbLut::new_constraint(&backgrounds, |c1, c2| HyAB(c1, &color_lut.get(c2)));
+        let apca_constraint = move |c| {
+            bgs.iter()
+                .map(|bg| APCA(&c, bg))
+                .fold(f32::INFINITY, f32::min)
+        };
+        let sim = move |kind| move |c| simulate(cvd_model, kind, severity, c);
+        Self {
+            gamut: gamut,
+            cvd_model: cvd_model,
+            severity: severity,
+            color: make_lut(storage[0], Oklab::from),
+            prot: make_lut(storage[1], sim(Deficiency::Protan)),
+            deut: make_lut(storage[2], sim(Deficiency::Deutan)),
+            trit: make_lut(storage[3], sim(Deficiency::Tritan)),
+            apca_constraint: make_lut(storage[4], apca_constraint),
+        }
+    }
+
+    // APCA is only defined for sRGB, so colors outside it are measured by their clipped sRGB fallback.
+    fn wide_gamut(
+        storage: [LutStorage; 5],
+        gamut: Gamut,
+        cvd_model: CvdModel,
+        severity: f32,
+    ) -> Self {
+        let decode = move |c| Oklab::from(RgbColor::from_8bit(gamut, c));
+        let bgs = [sRGB([0x00, 0x00, 0x00]), sRGB([0xFF, 0xFF, 0xFF])];
+        let apca_constraint = move |c| {
+            let fallback = RgbColor::from_8bit(gamut, c).srgb_fallback();
+            bgs.iter()
+                .map(|bg| APCA(&fallback, bg))
+                .fold(f32::INFINITY, f32::min)
+        };
+        let sim = move |kind| move |c| decode(c).simulate(cvd_model, kind, severity);
+        Self {
+            gamut: gamut,
+            cvd_model: cvd_model,
+            severity: severity,
+            color: make_lut(storage[0], decode),
+            prot: make_lut(storage[1], sim(Deficiency::Protan)),
+            deut: make_lut(storage[2], sim(Deficiency::Deutan)),
+            trit: make_lut(storage[3], sim(Deficiency::Tritan)),
+            apca_constraint: make_lut(storage[4], apca_constraint),
+        }
+    }
+}
+
+impl Default for Luts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The final score of one metric.
+#[derive(Clone, Copy, Debug)]
+pub struct MetricScore {
+    pub name: &'static str,
+    pub score: f32,
+    pub weighted: f32,
+    // Where the score comes from, as indices into the palette.
+    pub limiting: Metric,
+}
+
+#[derive(Clone, Debug)]
+pub struct JobResult {
+    pub palette: Vec<sRGB>,
+    // Objective score of the palette.
+    pub score: f32,
+    // In the order of `Job::metric_names`.
+    pub metrics: Vec<MetricScore>,
+    // Seed the run was started with, if it was optimized at all.
+    pub seed: Option<u64>,
+    pub iterations: u64,
+    pub elapsed: Duration,
+}
+
+// Everything needed to optimize a palette, built up with chained calls:
+//     Job::new(&luts, 8).weights(weights).schedule(schedule).run(&mut ())
+// Serializes to its settings, for recording alongside results.
+#[derive(Serialize)]
+pub struct Job<'a> {
+    #[serde(skip)]
+    luts: &'a Luts,
+    gamut: Gamut,
+    cvd_model: CvdModel,
+    severity: f32,
+    size: usize,
+    existing: Vec<sRGB>,
+    sequential: bool,
+    diverging: bool,
+    weights: [f32; 5],
+    importance: Option<PairImportance>,
+    objective: Objective,
+    schedule: Schedule,
+    only_metric: Option<usize>,
+    region: Option<&'a Region>,
+    init: Init,
+    continuous: bool,
+    translucency: Option<Translucency>,
+}
+
+impl<'a> Job<'a> {
+    // A categorical palette of `size` colors, with the default weights.
+    pub fn new(luts: &'a Luts, size: usize) -> Self {
+        Self {
+            luts: luts,
+            gamut: luts.gamut,
+            cvd_model: luts.cvd_model,
+            severity: luts.severity,
+            size: size,
+            existing: vec![],
+            sequential: false,
+            diverging: false,
+            weights: DEFAULT_WEIGHTS,
+            importance: None,
+            objective: Objective::Min,
+            schedule: Schedule::new(100000000),
+            only_metric: None,
+            region: None,
+            init: Init::Random,
+            continuous: false,
+            translucency: None,
+        }
+    }
+
+    // Ordered from dark to light, scoring only adjacent colors.
+    // Sequential palettes have no background constraint, so only the first four weights are used.
+    // Sorting would move existing colors, so they can't be combined.
+    pub fn sequential(mut self) -> Self {
+        assert!(
+            self.existing.is_empty(),
+            "Sequential palettes can't have existing colors"
+        );
+        self.sequential = true;
+        self
+    }
+
+    // Dark to light to dark: two sequential ramps sharing the lightest color in the middle, like a
+    // heatmap centered on zero. Each color is also scored against its mirror on the other ramp,
+    // so the two sides, and especially the endpoints, stay apart with CVD.
+    pub fn diverging(mut self) -> Self {
+        assert!(
+            self.size >= 3 && self.size % 2 == 1,
+            "Diverging palettes need an odd number of colors, at least 3"
+        );
+        self = self.sequential();
+        self.diverging = true;
+        self
+    }
+
+    // These colors start the palette and are never changed. At least one color must be left to optimize.
+    pub fn existing(mut self, existing: Vec<sRGB>) -> Self {
+        assert!(
+            existing.len() < self.size,
+            "Need at least one new color besides the existing ones"
+        );
+        assert!(
+            !self.sequential,
+            "Sequential palettes can't have existing colors"
+        );
+        self.existing = existing;
+        self
+    }
+
+    // Scores are divided by the weights, so each must be positive.
+    pub fn weights(mut self, weights: [f32; 5]) -> Self {
+        assert!(
+            weights.iter().all(|w| w.is_finite() && *w > 0.0),
+            "Weights must be positive and finite"
+        );
+        self.weights = weights;
+        self
+    }
+
+    pub fn importance(mut self, importance: PairImportance) -> Self {
+        assert_eq!(
+            importance.len(),
+            self.size,
+            "Importance matrix doesn't match the palette size"
+        );
+        self.importance = Some(importance);
+        self
+    }
+
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    // Optimize only the `k`th metric, ignoring all others and their weights.
+    pub fn only_metric(mut self, k: usize) -> Self {
+        assert!(k < self.metric_count(), "No metric {}", k);
+        self.only_metric = Some(k);
+        self
+    }
+
+    // Every color to be optimized is kept inside `region`. Existing colors may lie outside it.
+    pub fn region(mut self, region: &'a Region) -> Self {
+        assert_eq!(
+            region.gamut(),
+            self.gamut,
+            "Region is for a different gamut than the LUTs"
+        );
+        self.region = Some(region);
+        self
+    }
+
+    pub fn init(mut self, init: Init) -> Self {
+        self.init = init;
+        self
+    }
+
+    // Optimize with `ContinuousOptimizer` instead of `Optimizer`. Each evaluation counts as an iteration.
+    // Only categorical jobs with the `Min` objective and no region are supported.
+    pub fn continuous(mut self) -> Self {
+        self.continuous = true;
+        self
+    }
+
+    // Colors are drawn with the given opacity over each background, and scored as they are seen there.
+    // APCA contrast is against the background each color is drawn over. Categorical sRGB palettes only.
+    pub fn translucent(mut self, translucency: Translucency) -> Self {
+        assert_eq!(
+            self.gamut,
+            Gamut::Srgb,
+            "Translucency is only supported in sRGB"
+        );
+        self.translucency = Some(translucency);
+        self
+    }
+
+    pub fn gamut(&self) -> Gamut {
+        self.gamut
+    }
+
+    pub fn cvd_model(&self) -> CvdModel {
+        self.cvd_model
+    }
+
+    pub fn severity(&self) -> f32 {
+        self.severity
+    }
+
+    pub fn get_schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    fn metric_count(&self) -> usize {
+        if self.sequential {
+            PAIR_METRIC_NAMES.len()
+        } else {
+            PAIR_METRIC_NAMES.len() + CONSTRAINT_NAMES.len()
+        }
+    }
+
+    pub fn metric_names(&self) -> Vec<&'static str> {
+        let names = PAIR_METRIC_NAMES
+            .iter()
+            .chain(CONSTRAINT_NAMES.iter())
+            .copied()
+            .take(self.metric_count())
+            .collect_vec();
+        match self.only_metric {
+            Some(k) => vec![names[k]],
+            None => names,
+        }
+    }
+
+    // The existing colors followed by new ones, picked as given by `init`.
+    pub fn initial_colors(&self) -> Vec<sRGB> {
+        self.initial_colors_from(&mut thread_rng())
+    }
+
+    fn initial_colors_from(&self, rng: &mut impl Rng) -> Vec<sRGB> {
+        let luts = self.luts;
+        let spaces: [&dyn ColorLut<Oklab>; 4] = [&luts.color, &luts.prot, &luts.deut, &luts.trit];
+        let new_colors = initial_colors(
+            &self.init,
+            &self.existing,
+            self.size - self.existing.len(),
+            &spaces,
+            self.region,
+            rng,
+        );
+        let mut colors = self
+            .existing
+            .iter()
+            .copied()
+            .chain(new_colors)
+            .collect_vec();
+        if self.sequential {
+            // Starting from lightness order means every step is already valid.
+            colors.sort_by(|c1, c2| {
+                let (l1, l2) = (luts.color.get(c1).L, luts.color.get(c2).L);
+                l1.partial_cmp(&l2).unwrap()
+            });
+        }
+        if self.diverging {
+            // Every other color goes up the first ramp, and the rest come down the second.
+            let (lightest, rest) = colors.split_last().unwrap();
+            colors = rest
+                .iter()
+                .step_by(2)
+                .chain([lightest])
+                .chain(rest.iter().skip(1).step_by(2).rev())
+                .copied()
+                .collect_vec();
+        }
+        colors
+    }
+
+    pub(crate) fn build_optimizer(&self, colors: Vec<sRGB>) -> Optimizer<'a> {
+        let optimizer = self.build_unconstrained(colors);
+        match self.region {
+            Some(region) => optimizer.with_region(region),
+            None => optimizer,
+        }
+    }
+
+    // Scoring doesn't need the colors to be inside the region.
+    fn build_unconstrained(&self, colors: Vec<sRGB>) -> Optimizer<'a> {
+        assert_eq!(colors.len(), self.size, "Wrong number of colors");
+        let fixed = self.existing.len();
+        let luts = self.luts;
+        let oklab_luts = [&luts.color, &luts.prot, &luts.deut, &luts.trit];
+        assert!(
+            !(self.sequential && self.translucency.is_some()),
+            "Sequential palettes can't be translucent"
+        );
+        let mut pair_metrics = if self.sequential {
+            oklab_luts
+                .into_iter()
+                .map(|lut| {
+                    PairMetric::Adjacent(if self.diverging {
+                        AdjacentDistance::diverging(&colors, lut)
+                    } else {
+                        AdjacentDistance::new(&colors, lut)
+                    })
+                })
+                .collect_vec()
+        } else {
+            let importance = self
+                .importance
+                .clone()
+                .unwrap_or_else(|| PairImportance::uniform(self.size));
+            oklab_luts
+                .into_iter()
+                .map(|lut| match &self.translucency {
+                    Some(t) => {
+                        composited_distance(&colors, fixed, importance.clone(), lut, t.clone())
+                    }
+                    None => PairMetric::Distance(PairDistance::new(
+                        &colors,
+                        fixed,
+                        importance.clone(),
+                        lut,
+                    )),
+                })
+                .collect_vec()
+        };
+        let mut constraints = match &self.translucency {
+            _ if self.sequential => vec![],
+            Some(t) => vec![Constraint::composited(&colors, fixed, t.clone(), APCA)],
+            None => vec![Constraint::new(&colors, fixed, &luts.apca_constraint)],
+        };
+
+        let optimizer = match self.only_metric {
+            Some(k) if k < pair_metrics.len() => {
+                let metric = pair_metrics.swap_remove(k);
+                Optimizer::new(vec![(1.0, metric)], vec![], colors, fixed)
+            }
+            Some(k) => {
+                let constraint = constraints.swap_remove(k - pair_metrics.len());
+                Optimizer::new(vec![], vec![(1.0, constraint)], colors, fixed)
+            }
+            None => {
+                let (pair_weights, constraint_weights) = self.weights.split_at(pair_metrics.len());
+                Optimizer::new(
+                    pair_weights.iter().copied().zip(pair_metrics).collect(),
+                    constraint_weights
+                        .iter()
+                        .copied()
+                        .zip(constraints)
+                        .collect(),
+                    colors,
+                    fixed,
+                )
+            }
+        };
+        optimizer.with_objective(self.objective)
+    }
+
+    // Scores `colors` without optimizing them.
+    pub fn evaluate(&self, colors: Vec<sRGB>) -> JobResult {
+        self.make_result(colors, None, 0, Duration::ZERO)
+    }
+
+    fn make_result(
+        &self,
+        palette: Vec<sRGB>,
+        seed: Option<u64>,
+        iterations: u64,
+        elapsed: Duration,
+    ) -> JobResult {
+        let optimizer = self.build_unconstrained(palette.clone());
+        let metrics = self
+            .metric_names()
+            .into_iter()
+            .zip(optimizer.get_metric_scores())
+            .map(|(name, (score, weighted, limiting))| MetricScore {
+                name: name,
+                score: score,
+                weighted: weighted,
+                limiting: limiting,
+            })
+            .collect_vec();
+        JobResult {
+            palette: palette,
+            score: optimizer.get_best_score(),
+            metrics: metrics,
+            seed: seed,
+            iterations: iterations,
+            elapsed: elapsed,
+        }
+    }
+
+    // Optimizes from random colors, returning the best palette found.
+    pub fn run(&self, observer: &mut dyn Observer) -> JobResult {
+        self.run_seeded(rand::random(), observer)
+    }
+
+    // Runs with the same seed give the same result, unless the time limit stops them.
+    pub fn run_seeded(&self, seed: u64, observer: &mut dyn Observer) -> JobResult {
+        let colors = self.initial_colors_from(&mut StdRng::seed_from_u64(seed));
+        self.run_from_seeded(colors, seed, observer)
+    }
+
+    // Optimizes from the given colors, which must start with the existing ones.
+    pub fn run_from(&self, colors: Vec<sRGB>, observer: &mut dyn Observer) -> JobResult {
+        self.run_from_seeded(colors, rand::random(), observer)
+    }
+
+    pub fn run_from_seeded(
+        &self,
+        colors: Vec<sRGB>,
+        seed: u64,
+        observer: &mut dyn Observer,
+    ) -> JobResult {
+        assert!(
+            colors.starts_with(&self.existing),
+            "Colors don't start with the existing colors"
+        );
+        let start_time = Instant::now();
+        let (iterations, best) = if self.continuous {
+            let mut optimizer = self.build_continuous(colors).with_seed(seed);
+            (
+                optimizer.run(&self.schedule, observer),
+                optimizer.get_best().1,
+            )
+        } else {
+            let mut optimizer = self.build_optimizer(colors).with_seed(seed);
+            (
+                optimizer.run(&self.schedule, observer),
+                optimizer.get_best().1,
+            )
+        };
+        let elapsed = start_time.elapsed();
+        self.make_result(best, Some(seed), iterations, elapsed)
+    }
+
+    fn build_continuous(&self, colors: Vec<sRGB>) -> ContinuousOptimizer {
+        assert_eq!(colors.len(), self.size, "Wrong number of colors");
+        assert!(
+            !self.sequential
+                && matches!(self.objective, Objective::Min)
+                && self.only_metric.is_none()
+                && self.region.is_none()
+                && self.translucency.is_none(),
+            "The continuous optimizer only supports categorical palettes with the plain minimum objective"
+        );
+        let importance = self
+            .importance
+            .clone()
+            .unwrap_or_else(|| PairImportance::uniform(self.size));
+        ContinuousOptimizer::new(
+            colors,
+            self.existing.len(),
+            importance,
+            self.weights,
+            self.gamut,
+            self.cvd_model,
+            self.severity,
+        )
+    }
+
+    // How the palette looks on displays without the wide gamut, where each color is clipped to
+    // the nearest sRGB one. `srgb_luts` score the fallback colors, so must be for sRGB,
+    // with the same CVD simulation as this job.
+    pub fn srgb_fallback(&self, palette: &Vec<sRGB>, srgb_luts: &Luts) -> JobResult {
+        assert_eq!(
+            srgb_luts.gamut,
+            Gamut::Srgb,
+            "Fallback LUTs must be for sRGB"
+        );
+        assert_eq!(
+            (srgb_luts.cvd_model, srgb_luts.severity),
+            (self.cvd_model, self.severity),
+            "Fallback LUTs must use the same CVD simulation"
+        );
+        let fallback = |colors: &Vec<sRGB>| {
+            colors
+                .iter()
+                .map(|c| RgbColor::from_8bit(self.gamut, *c).srgb_fallback())
+                .collect_vec()
+        };
+        let job = Job {
+            luts: srgb_luts,
+            gamut: Gamut::Srgb,
+            existing: fallback(&self.existing),
+            importance: self.importance.clone(),
+            region: None,
+            init: self.init.clone(),
+            translucency: self.translucency.clone(),
+            ..*self
+        };
+        job.evaluate(fallback(palette))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_shareable<T: Send + Sync>() {}
+
+    #[test]
+    fn test_job() {
+        // Shared between jobs, including jobs on other threads.
+        assert_shareable::<Luts>();
+        let luts = Luts::new();
+        let existing = vec![sRGB([0x00, 0x00, 0x00]), sRGB([0xFF, 0xFF, 0xFF])];
+        let job = Job::new(&luts, 4)
+            .existing(existing.clone())
+            .schedule(Schedule::new(10000));
+        let result = job.run(&mut ());
+
+        assert!(result.palette.starts_with(&existing));
+        assert_eq!(result.palette.len(), 4);
+        assert_eq!(result.iterations, 10000);
+        assert_eq!(result.metrics.len(), 5);
+        let min_weighted = result
+            .metrics
+            .iter()
+            .map(|m| m.weighted)
+            .fold(f32::INFINITY, f32::min);
+        assert_eq!(result.score, min_weighted);
+        assert_eq!(job.evaluate(result.palette.clone()).score, result.score);
+
+        let seed = result.seed.unwrap();
+        assert_eq!(job.run_seeded(seed, &mut ()).palette, result.palette);
+    }
+
+    #[test]
+    fn test_diverging() {
+        let luts = Luts::with_storage([LutStorage::Cached; 5]);
+        let job = Job::new(&luts, 5)
+            .diverging()
+            .schedule(Schedule::new(20000));
+        let result = job.run_seeded(1, &mut ());
+        assert_eq!(result.metrics.len(), 4);
+        assert!(result.score > 0.0);
+        let l = result
+            .palette
+            .iter()
+            .map(|c| Oklab::from(*c).L)
+            .collect_vec();
+        assert!(l[0] < l[1] && l[1] < l[2] && l[2] > l[3] && l[3] > l[4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Need at least one new color")]
+    fn test_existing_fills_palette() {
+        let luts = Luts::with_storage([LutStorage::Cached; 5]);
+        Job::new(&luts, 2).existing(vec![sRGB([0x00, 0x00, 0x00]), sRGB([0xFF, 0xFF, 0xFF])]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sequential palettes can't have existing colors")]
+    fn test_sequential_existing() {
+        let luts = Luts::with_storage([LutStorage::Cached; 5]);
+        Job::new(&luts, 4)
+            .existing(vec![sRGB([0xFF, 0xFF, 0xFF])])
+            .sequential();
+    }
+
+    #[test]
+    #[should_panic(expected = "Weights must be positive and finite")]
+    fn test_zero_weight() {
+        let luts = Luts::with_storage([LutStorage::Cached; 5]);
+        Job::new(&luts, 4).weights([1.0, 1.0, 0.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,27 @@
+extern crate color_lib;
+
+mod compare;
+mod continuous;
+mod init;
+mod job;
+mod lut;
+mod metric;
+mod optimizer;
+mod pareto;
+mod region;
+mod score;
+mod update;
+
+// The stable API is the `Job` builder and what it takes and returns.
+// The optimizers, metrics and lookup tables behind it are internal and may change.
+pub use compare::{group_similar, match_palettes, PaletteMatch};
+pub use init::Init;
+pub use job::{
+    Job, JobResult, Luts, MetricScore, CONSTRAINT_NAMES, DEFAULT_WEIGHTS, PAIR_METRIC_NAMES,
+};
+pub use lut::LutStorage;
+pub use metric::Translucency;
+pub use optimizer::{Control, Metric, Objective, Observer, Schedule, Snapshot};
+pub use pareto::ParetoArchive;
+pub use region::{OklchBounds, Region};
+pub use score::PairImportance;
@@ -0,0 +1,231 @@
+use std::sync::Mutex;
+
+use color_lib::{as_index, sRGB, Oklab};
+use itertools::iproduct;
+use serde::Serialize;
+
+// Anything that maps every sRGB color to a value, however it is stored.
+pub trait ColorLut<T> {
+    fn get(&self, c: &sRGB) -> T;
+}
+
+pub struct SrgbLut<T> {
+    data: Vec<T>,
+}
+
+impl<T: Copy> SrgbLut<T> {
+    pub fn new(f: impl Fn(sRGB) -> T) -> Self {
+        let mut data = Vec::with_capacity(1 << 24);
+        // I wish there was an easy way to allow this to be parallel,
+        // But it is fast enough that it isn't a significant issue.
+        for (r, g, b) in iproduct!(0x00..=0xFF, 0x00..=0xFF, 0x00..=0xFF) {
+            let c = sRGB([r, g, b]);
+            data.push(f(c))
+        }
+        Self { data: data }
+    }
+
+    pub fn get(&self, c: &sRGB) -> T {
+        self.data[as_index(c)]
+    }
+}
+
+impl SrgbLut<f32> {
+    #[allow(dead_code)]
+    pub fn new_constraint<T2>(backgrounds: &Vec<T2>, f: impl Fn(&T2, &sRGB) -> f32) -> Self {
+        Self::new(|c| {
+            backgrounds
+                .iter()
+                .map(|bg| f(bg, &c))
+                .min_by(|x, y| x.partial_cmp(y).unwrap())
+                .unwrap()
+        })
+    }
+}
+
+impl<T: Copy> ColorLut<T> for SrgbLut<T> {
+    fn get(&self, c: &sRGB) -> T {
+        SrgbLut::get(self, c)
+    }
+}
+
+// Steps of 1/256 keep the error far below anything visible, while the Oklab coordinates,
+// which are scaled to 0 to 100, and APCA contrasts still fit in an i16.
+const QUANT_SCALE: f32 = 256.0;
+
+fn quantize(x: f32) -> i16 {
+    (x * QUANT_SCALE)
+        .round()
+        .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+fn dequantize(x: i16) -> f32 {
+    x as f32 / QUANT_SCALE
+}
+
+pub trait Quantize: Copy {
+    type Stored: Copy;
+
+    fn quantize(self) -> Self::Stored;
+
+    fn dequantize(stored: Self::Stored) -> Self;
+}
+
+impl Quantize for f32 {
+    type Stored = i16;
+
+    fn quantize(self) -> i16 {
+        quantize(self)
+    }
+
+    fn dequantize(stored: i16) -> Self {
+        dequantize(stored)
+    }
+}
+
+impl Quantize for Oklab {
+    type Stored = [i16; 3];
+
+    fn quantize(self) -> [i16; 3] {
+        [quantize(self.L), quantize(self.a), quantize(self.b)]
+    }
+
+    fn dequantize(stored: [i16; 3]) -> Self {
+        Oklab {
+            L: dequantize(stored[0]),
+            a: dequantize(stored[1]),
+            b: dequantize(stored[2]),
+        }
+    }
+}
+
+// Like `SrgbLut`, but stored as i16s, which halves the memory.
+pub struct QuantizedLut<T: Quantize> {
+    data: SrgbLut<T::Stored>,
+}
+
+impl<T: Quantize> QuantizedLut<T> {
+    pub fn new(f: impl Fn(sRGB) -> T) -> Self {
+        Self {
+            data: SrgbLut::new(|c| f(c).quantize()),
+        }
+    }
+}
+
+impl<T: Quantize> ColorLut<T> for QuantizedLut<T> {
+    // Kept out of line, like `CachedLut::get`, so that `Lut::get` stays small where it's inlined.
+    #[inline(never)]
+    fn get(&self, c: &sRGB) -> T {
+        T::dequantize(self.data.get(c))
+    }
+}
+
+const CACHE_BITS: u32 = 16;
+
+// Computes values as they are needed, remembering recent ones in a small direct-mapped cache.
+// The optimizer only moves a few colors one step at a time, so most lookups hit the cache.
+// The cache is behind a mutex so that tables can be shared between threads, at the cost of contention.
+pub struct CachedLut<T> {
+    f: Box<dyn Fn(sRGB) -> T + Send + Sync>,
+    cache: Mutex<Vec<Option<(u32, T)>>>,
+}
+
+impl<T: Copy> CachedLut<T> {
+    pub fn new(f: impl Fn(sRGB) -> T + Send + Sync + 'static) -> Self {
+        Self {
+            f: Box::new(f),
+            cache: Mutex::new(vec![None; 1 << CACHE_BITS]),
+        }
+    }
+}
+
+impl<T: Copy> ColorLut<T> for CachedLut<T> {
+    // Kept out of line, since inlining it into every metric's `Lut::get` slows down dense tables too.
+    #[inline(never)]
+    fn get(&self, c: &sRGB) -> T {
+        let index = as_index(c) as u32;
+        // Fibonacci hashing, so neighboring colors land in different slots.
+        let slot = (index.wrapping_mul(0x9E3779B9) >> (32 - CACHE_BITS)) as usize;
+        let mut cache = self.cache.lock().unwrap();
+        match cache[slot] {
+            Some((i, value)) if i == index => value,
+            _ => {
+                let value = (self.f)(*c);
+                cache[slot] = Some((index, value));
+                value
+            }
+        }
+    }
+}
+
+// How a lookup table is stored. Dense tables are the fastest, but an Oklab one takes about 200 MB.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum LutStorage {
+    Dense,
+    // About half the memory of `Dense`.
+    Quantized,
+    // About 1.3 MB for Oklab values, but slower.
+    Cached,
+}
+
+// A table in any of the storages. The metrics look colors up through this instead of a
+// `dyn ColorLut`, so the default dense tables cost a predictable branch rather than a virtual call.
+pub enum Lut<T: Quantize> {
+    Dense(SrgbLut<T>),
+    Quantized(QuantizedLut<T>),
+    Cached(CachedLut<T>),
+}
+
+impl<T: Quantize> Lut<T> {
+    #[inline]
+    pub fn get(&self, c: &sRGB) -> T {
+        match self {
+            Lut::Dense(lut) => lut.get(c),
+            Lut::Quantized(lut) => lut.get(c),
+            Lut::Cached(lut) => lut.get(c),
+        }
+    }
+}
+
+impl<T: Quantize> ColorLut<T> for Lut<T> {
+    fn get(&self, c: &sRGB) -> T {
+        Lut::get(self, c)
+    }
+}
+
+pub fn make_lut<T: Quantize>(
+    storage: LutStorage,
+    f: impl Fn(sRGB) -> T + Send + Sync + 'static,
+) -> Lut<T> {
+    match storage {
+        LutStorage::Dense => Lut::Dense(SrgbLut::new(f)),
+        LutStorage::Quantized => Lut::Quantized(QuantizedLut::new(f)),
+        LutStorage::Cached => Lut::Cached(CachedLut::new(f)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_luts() {
+        let quantized = QuantizedLut::new(Oklab::from);
+        let cached = CachedLut::new(Oklab::from);
+        for c in [
+            sRGB([0x00, 0x00, 0x00]),
+            sRGB([0xFF, 0xFF, 0xFF]),
+            sRGB([0xFA, 0x35, 0xEC]),
+            sRGB([0x3B, 0xDB, 0x78]),
+        ] {
+            let exact = Oklab::from(c);
+            let q = quantized.get(&c);
+            assert!((q.L - exact.L).abs() <= 0.5 / QUANT_SCALE);
+            assert!((q.a - exact.a).abs() <= 0.5 / QUANT_SCALE);
+            assert!((q.b - exact.b).abs() <= 0.5 / QUANT_SCALE);
+            // Once to fill the cache, once to hit it.
+            assert_eq!(cached.get(&c).L, exact.L);
+            assert_eq!(cached.get(&c).L, exact.L);
+        }
+    }
+}
@@ -1,18 +1,24 @@
 extern crate color_lib;
+extern crate palette_optimizer;
 extern crate palette_visualizer;
 
-mod metric;
-mod optimizer;
-mod score;
-mod update;
-
 use color_lib::*;
 use indicatif::{ProgressBar, ProgressStyle};
 use itertools::Itertools;
-use metric::*;
-use optimizer::Optimizer;
-use palette_visualizer::save_svg;
-use std::{iter::repeat_with, time::Instant};
+use palette_optimizer::{
+    group_similar, Control, Init, Job, JobResult, LutStorage, Luts, Metric, Objective, Observer,
+    OklchBounds, PairImportance, ParetoArchive, Region, Schedule, Snapshot, Translucency,
+    CONSTRAINT_NAMES, DEFAULT_WEIGHTS, PAIR_METRIC_NAMES,
+};
+use palette_visualizer::{save_scatter, save_svg};
+use rand::{thread_rng, Rng};
+use serde::Serialize;
+use std::{
+    env,
+    fs::File,
+    io::{BufWriter, Write},
+    time::Duration,
+};
 
 #[allow(dead_code)]
 fn breakpoint() {
@@ -20,72 +26,838 @@ fn breakpoint() {
     std::io::stdin().read_line(&mut buf).unwrap();
 }
 
-fn main() {
-    let bgs = [[0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF]];
-    // let backgrounds = bgs.iter().map(|c| (*c).into()).collect_vec();
-    let color_lut = SrgbLut::new(Oklab::from);
-    let prot_lut = SrgbLut::new(simulate_protan);
-    let deut_lut = SrgbLut::new(simulate_deutan);
-    let trit_lut = SrgbLut::new(simulate_tritan);
-    // let constraint_lut =
-    //     SrgbLut::new_constraint(&backgrounds, |c1, c2| HyAB(c1, &color_lut.get(c2)));
-    let apca_constraint_lut = SrgbLut::new_constraint(&bgs.to_vec(), |c1, c2| APCA(c2, c1));
-
-    let num_iter: u64 = 1000000000;
-    let broad_iter = num_iter / 2;
-    let small_iter: u64 = 10000;
-    let update_freq: u64 = 1000000;
-    // breakpoint();
-    for big_num in 0..4 {
-        let colors = repeat_with(rand::random).take(8).collect_vec();
-        let mut optimizer = Optimizer::new(
-            vec![
-                (25.0, PairDistance::new(&colors, &color_lut)),
-                (20.0, PairDistance::new(&colors, &prot_lut)),
-                (20.0, PairDistance::new(&colors, &deut_lut)),
-                (15.0, PairDistance::new(&colors, &trit_lut)),
-            ],
-            vec![(30.0, Constraint::new(&colors, &apca_constraint_lut))],
-            colors,
+const NUM_ITER: u64 = 1000000000;
+// Restarts whose palettes match with no color moved further than this HyAB distance count as the same solution.
+const SAME_WITHIN: f32 = 3.0;
+
+fn describe_metric(m: Metric, colors: &Vec<sRGB>) -> (&'static str, String) {
+    match m {
+        Metric::Pair(i, (a, b)) => (PAIR_METRIC_NAMES[i], format!("{} {}", colors[a], colors[b])),
+        Metric::Const(i, j) => (CONSTRAINT_NAMES[i], colors[j].to_string()),
+    }
+}
+
+// Prints every metric of `result`, and returns the minimum weighted score.
+fn print_scores(result: &JobResult) -> f32 {
+    let mut min_score = f32::INFINITY;
+    println!("metric\tscore\tweighted\tlimiting");
+    for m in result.metrics.iter() {
+        let (_, limiting) = describe_metric(m.limiting, &result.palette);
+        println!(
+            "{}\t{:.3}\t{:.5}\t{}",
+            m.name, m.score, m.weighted, limiting
         );
+        min_score = min_score.min(m.weighted);
+    }
+    println!("min\t\t{:.5}", min_score);
+    min_score
+}
 
-        let start_time = Instant::now();
-        let pb = ProgressBar::new(num_iter).with_style(ProgressStyle::with_template(
-            "{elapsed_precise}/{duration_precise} {wide_bar} {percent:>02}% {pos}/{len} {per_sec}",
-        ).unwrap());
-
-        let mut counter = 0;
-        let mut best_score = optimizer.get_best_score();
-        for it in 0..num_iter {
-            optimizer.update();
-            if it >= broad_iter {
-                counter += 1;
-                if optimizer.get_best_score() > best_score {
-                    counter = 0;
-                    best_score = optimizer.get_best_score();
-                } else if counter >= small_iter {
-                    counter = 0;
-                    optimizer.restore_best();
-                }
+// cargo run -p palette-optimizer --release -- score --threshold 0.5 FA35EC 9449FF 3BDB78 BF18BE
+// cargo run -p palette-optimizer --release -- score --cvd-model machado --severity 0.6 FA35EC 9449FF 3BDB78
+// cargo run -p palette-optimizer --release -- score --weights 1,1,1,1,2 FA35EC 9449FF 3BDB78
+fn score(args: &[String]) {
+    let mut threshold = None;
+    let mut weights = DEFAULT_WEIGHTS;
+    let mut cvd_model = CvdModel::Brettel1997;
+    let mut severity = 1.0;
+    let mut colors = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--threshold" {
+            let value = args.next().expect("Missing value for --threshold");
+            threshold = Some(value.parse::<f32>().expect("Invalid threshold"));
+        } else if arg == "--weights" {
+            weights = parse_weights(args.next().expect("Missing weights"));
+        } else if arg.starts_with("--") {
+            let value = args.next().expect("Missing option value");
+            if !parse_cvd_option(&mut cvd_model, &mut severity, arg, value) {
+                panic!("Unknown option {}", arg)
+            }
+        } else {
+            colors.push(arg.parse().expect("Invalid color"));
+        }
+    }
+    if colors.len() < 2 {
+        panic!("Need at least two colors to score!")
+    }
+
+    // Only a few colors are looked up, so building dense tables would take far longer than scoring.
+    let luts = Luts::for_cvd([LutStorage::Cached; 5], Gamut::Srgb, cvd_model, severity);
+    let result = Job::new(&luts, colors.len())
+        .weights(weights)
+        .evaluate(colors);
+    let min_score = print_scores(&result);
+
+    if let Some(t) = threshold {
+        if min_score < t {
+            eprintln!("Score {} is below threshold {}", min_score, t);
+            std::process::exit(1);
+        }
+    }
+}
+
+// cargo run -p palette-optimizer --release -- extend 2 FA35EC 9449FF 3BDB78 BF18BE
+// cargo run -p palette-optimizer --release -- extend 2 --cvd-model vienot FA35EC 9449FF 3BDB78 BF18BE
+// cargo run -p palette-optimizer --release -- extend 2 --time 60 FA35EC 9449FF 3BDB78 BF18BE
+fn extend(args: &[String]) {
+    let n = args
+        .first()
+        .expect("Missing number of colors to add")
+        .parse::<usize>()
+        .expect("Invalid number of colors to add");
+    let mut cvd_model = CvdModel::Brettel1997;
+    let mut severity = 1.0;
+    let mut schedule = Schedule::new(NUM_ITER);
+    let mut existing = vec![];
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--") {
+            let value = args.next().expect("Missing option value");
+            if !parse_schedule_option(&mut schedule, arg, value)
+                && !parse_cvd_option(&mut cvd_model, &mut severity, arg, value)
+            {
+                panic!("Unknown option {}", arg)
+            }
+        } else {
+            existing.push(arg.parse().expect("Invalid color"));
+        }
+    }
+    if n == 0 {
+        panic!("Need to add at least one color!")
+    }
+
+    let luts = Luts::for_cvd([LutStorage::Dense; 5], Gamut::Srgb, cvd_model, severity);
+    let job = Job::new(&luts, existing.len() + n)
+        .existing(existing)
+        .schedule(schedule);
+    optimize(&job, None, None, SAME_WITHIN);
+}
+
+// cargo run -p palette-optimizer --release -- sequential 7
+// cargo run -p palette-optimizer --release -- sequential 7 --iter 20000000
+fn sequential(args: &[String]) {
+    let n = args
+        .first()
+        .expect("Missing number of colors")
+        .parse::<usize>()
+        .expect("Invalid number of colors");
+    let mut schedule = Schedule::new(NUM_ITER);
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().expect("Missing option value");
+        if !parse_schedule_option(&mut schedule, arg, value) {
+            panic!("Unknown option {}", arg)
+        }
+    }
+    if n < 2 {
+        panic!("Need at least two colors!")
+    }
+
+    let luts = Luts::new();
+    let job = Job::new(&luts, n).sequential().schedule(schedule);
+    optimize(&job, None, None, SAME_WITHIN);
+}
+
+// cargo run -p palette-optimizer --release -- diverging 9
+// cargo run -p palette-optimizer --release -- diverging 9 --patience 50000000
+fn diverging(args: &[String]) {
+    let n = args
+        .first()
+        .expect("Missing number of colors")
+        .parse::<usize>()
+        .expect("Invalid number of colors");
+    let mut schedule = Schedule::new(NUM_ITER);
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().expect("Missing option value");
+        if !parse_schedule_option(&mut schedule, arg, value) {
+            panic!("Unknown option {}", arg)
+        }
+    }
+    if n < 3 || n % 2 == 0 {
+        panic!("Need an odd number of colors, at least 3!")
+    }
+
+    let luts = Luts::new();
+    let job = Job::new(&luts, n).diverging().schedule(schedule);
+    optimize(&job, None, None, SAME_WITHIN);
+}
+
+// Shows a progress bar with the best score so far, and optionally logs every snapshot as a line of JSON.
+struct ProgressObserver<'w> {
+    pb: ProgressBar,
+    run: usize,
+    log: Option<&'w mut dyn Write>,
+}
+
+impl Observer for ProgressObserver<'_> {
+    fn observe(&mut self, snapshot: &Snapshot) -> Control {
+        self.pb.set_position(snapshot.iteration);
+        self.pb.set_message(format!("{:.5}", snapshot.best_score));
+        if let Some(log) = self.log.as_mut() {
+            let (metric, limiting) = describe_metric(snapshot.limiting, snapshot.colors);
+            let line = serde_json::json!({
+                "run": self.run,
+                "iteration": snapshot.iteration,
+                "score": snapshot.score,
+                "best_score": snapshot.best_score,
+                "limiting_score": snapshot.limiting_score,
+                "limiting_metric": metric,
+                "limiting": limiting,
+            });
+            writeln!(log, "{}", line).unwrap();
+        }
+        Control::Continue
+    }
+}
+
+fn run_job(job: &Job, colors: Vec<sRGB>) -> JobResult {
+    run_with_progress(job, 0, None, |observer| job.run_from(colors, observer))
+}
+
+fn run_with_progress(
+    job: &Job,
+    run: usize,
+    log: Option<&mut dyn Write>,
+    start: impl FnOnce(&mut dyn Observer) -> JobResult,
+) -> JobResult {
+    let pb = ProgressBar::new(job.get_schedule().max_iter).with_style(
+        ProgressStyle::with_template(
+            "{elapsed_precise}/{duration_precise} {wide_bar} {percent:>02}% {pos}/{len} {per_sec} {msg}",
+        )
+        .unwrap(),
+    );
+    let mut observer = ProgressObserver {
+        pb: pb,
+        run: run,
+        log: log,
+    };
+    let result = start(&mut observer);
+    observer.pb.finish_and_clear();
+    result
+}
+
+// Either one storage for every metric, or one per metric, comma-separated.
+fn parse_lut_storage(value: &str) -> [LutStorage; 5] {
+    let storage = value
+        .split(',')
+        .map(|s| match s {
+            "dense" => LutStorage::Dense,
+            "quantized" => LutStorage::Quantized,
+            "cached" => LutStorage::Cached,
+            _ => panic!("Unknown LUT storage {}", s),
+        })
+        .collect_vec();
+    match storage.len() {
+        1 => [storage[0]; 5],
+        _ => storage.try_into().expect("Need 1 or 5 LUT storages"),
+    }
+}
+
+// Either a strategy name, or comma-separated colors to start from.
+fn parse_init(value: &str) -> Init {
+    match value {
+        "random" => Init::Random,
+        "farthest" => Init::FarthestPoint,
+        "kmeans++" => Init::KMeansPlusPlus,
+        "hues" => Init::HueWheel,
+        _ => Init::Palette(parse_colors(value)),
+    }
+}
+
+// Comma-separated colors. Commas inside parentheses belong to the color, as in "rgb(59, 219, 120)".
+fn parse_colors(value: &str) -> Vec<sRGB> {
+    let mut depth = 0;
+    value
+        .split(|ch| {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
             }
-            if it % update_freq == update_freq - 1 {
-                pb.inc(update_freq)
+            ch == ',' && depth == 0
+        })
+        .map(|c| c.parse().expect("Invalid color"))
+        .collect_vec()
+}
+
+fn parse_pair(value: &str) -> (f32, f32) {
+    let (a, b) = value
+        .split_once(',')
+        .expect("Expected two comma-separated numbers");
+    (
+        a.parse().expect("Invalid number"),
+        b.parse().expect("Invalid number"),
+    )
+}
+
+// Returns whether `arg` was a region option. Hues are in degrees, and --hue may be given more than once.
+fn parse_region_option(bounds: &mut OklchBounds, arg: &str, value: &str) -> bool {
+    match arg {
+        "--lightness" => bounds.lightness = Some(parse_pair(value)),
+        "--max-chroma" => bounds.max_chroma = Some(value.parse().expect("Invalid chroma")),
+        "--hue" => bounds.hues.push(parse_pair(value)),
+        _ => return false,
+    }
+    true
+}
+
+// Returns whether `arg` was a color vision option. Severity goes from 0 to 1, for dichromacy.
+fn parse_cvd_option(model: &mut CvdModel, severity: &mut f32, arg: &str, value: &str) -> bool {
+    match arg {
+        "--cvd-model" => *model = value.parse().unwrap(),
+        "--severity" => *severity = value.parse().expect("Invalid severity"),
+        _ => return false,
+    }
+    true
+}
+
+// Returns whether `arg` was a schedule option.
+fn parse_schedule_option(schedule: &mut Schedule, arg: &str, value: &str) -> bool {
+    match arg {
+        "--iter" => schedule.max_iter = value.parse().expect("Invalid iteration count"),
+        "--patience" => schedule.patience = Some(value.parse().expect("Invalid patience")),
+        "--time" => {
+            let seconds = value.parse::<f64>().expect("Invalid time limit");
+            schedule.time_limit = Some(Duration::from_secs_f64(seconds));
+        }
+        "--target" => schedule.target_score = Some(value.parse().expect("Invalid target score")),
+        "--broad-patience" => {
+            schedule.broad_patience = Some(value.parse().expect("Invalid broad patience"))
+        }
+        _ => return false,
+    }
+    true
+}
+
+// cargo run -p palette-optimizer --release -- largest --hyab 10 --apca 30
+// cargo run -p palette-optimizer --release -- largest --hyab 10 --apca 30 --cvd-model machado --severity 0.6
+fn largest(args: &[String]) {
+    let mut min_distance = None;
+    let mut min_contrast = None;
+    let mut num_iter: u64 = 100000000;
+    let mut cvd_model = CvdModel::Brettel1997;
+    let mut severity = 1.0;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().expect("Missing option value");
+        match arg.as_str() {
+            "--hyab" => min_distance = Some(value.parse::<f32>().expect("Invalid HyAB distance")),
+            "--apca" => min_contrast = Some(value.parse::<f32>().expect("Invalid APCA contrast")),
+            "--iter" => num_iter = value.parse().expect("Invalid iteration count"),
+            _ => {
+                if !parse_cvd_option(&mut cvd_model, &mut severity, arg, value) {
+                    panic!("Unknown option {}", arg)
+                }
             }
         }
-        pb.finish_and_clear();
-        let best = optimizer.get_best();
+    }
+    let min_distance = min_distance.expect("Missing --hyab");
+    let min_contrast = min_contrast.expect("Missing --apca");
+    // The thresholds divide the scores, so zero would make every palette score infinite.
+    assert!(min_distance > 0.0, "--hyab must be positive");
+    assert!(min_contrast > 0.0, "--apca must be positive");
+
+    // Weighting each metric by its threshold means a palette is feasible exactly when its score is at least 1.
+    let weights = [
+        min_distance,
+        min_distance,
+        min_distance,
+        min_distance,
+        min_contrast,
+    ];
+    let luts = Luts::for_cvd([LutStorage::Dense; 5], Gamut::Srgb, cvd_model, severity);
+
+    let mut feasible: Option<JobResult> = None;
+    let mut colors = Job::new(&luts, 2).initial_colors();
+    loop {
+        let job = Job::new(&luts, colors.len())
+            .weights(weights)
+            .schedule(Schedule::new(num_iter));
+        let result = run_job(&job, colors);
         println!(
             "{}:\t{:#?}\t{}\t{:?}",
+            result.palette.len(),
+            result.elapsed,
+            result.score,
+            result.palette.iter().map(sRGB::to_string).collect_vec()
+        );
+        if result.score < 1.0 {
+            break;
+        }
+        // Grow from the feasible palette instead of starting over.
+        colors = result
+            .palette
+            .iter()
+            .copied()
+            .chain(std::iter::once(sRGB(rand::random())))
+            .collect_vec();
+        feasible = Some(result);
+    }
+
+    match feasible {
+        None => println!("No feasible palette found."),
+        Some(result) => {
+            println!(
+                "Largest feasible palette has {} colors:",
+                result.palette.len()
+            );
+            print_scores(&result);
+            save_svg("largest.svg", result.palette).unwrap();
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ParetoPoint {
+    scores: Vec<f32>,
+    weights: [f32; 5],
+    colors: Vec<sRGB>,
+}
+
+#[derive(Serialize)]
+struct ParetoReport {
+    metrics: Vec<&'static str>,
+    front: Vec<ParetoPoint>,
+}
+
+// cargo run -p palette-optimizer --release -- pareto --runs 64 --iter 50000000
+fn pareto(args: &[String]) {
+    let mut runs = 32;
+    let mut num_iter: u64 = 100000000;
+    let mut n = 8;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().expect("Missing option value");
+        match arg.as_str() {
+            "--runs" => runs = value.parse().expect("Invalid number of runs"),
+            "--iter" => num_iter = value.parse().expect("Invalid iteration count"),
+            "--size" => n = value.parse().expect("Invalid palette size"),
+            _ => panic!("Unknown option {}", arg),
+        }
+    }
+
+    let luts = Luts::new();
+    let mut archive = ParetoArchive::new();
+    for run in 0..runs {
+        // Scale each default weight by up to a factor of e in either direction.
+        let mut weights = DEFAULT_WEIGHTS;
+        for w in weights.iter_mut() {
+            *w *= thread_rng().gen_range(-1.0_f32..1.0).exp();
+        }
+
+        let job = Job::new(&luts, n)
+            .weights(weights)
+            .schedule(Schedule::new(num_iter));
+        let result = run_job(&job, job.initial_colors());
+
+        // Compare palettes by their unweighted scores, so the weights used don't matter.
+        let scores = result.metrics.iter().map(|m| m.score).collect_vec();
+        let added = archive.insert(scores.clone(), (weights, result.palette));
+        println!(
+            "{}:\t{:?}\t{}\t{}",
+            run,
+            scores,
+            added,
+            archive.entries().len()
+        );
+    }
+
+    let names = PAIR_METRIC_NAMES
+        .iter()
+        .chain(CONSTRAINT_NAMES.iter())
+        .copied()
+        .collect_vec();
+    let report = ParetoReport {
+        metrics: names.clone(),
+        front: archive
+            .entries()
+            .iter()
+            .map(|(scores, (weights, colors))| ParetoPoint {
+                scores: scores.clone(),
+                weights: *weights,
+                colors: colors.clone(),
+            })
+            .collect_vec(),
+    };
+    let json = serde_json::to_string_pretty(&report).unwrap();
+    std::fs::write("pareto.json", json).unwrap();
+
+    let points = archive
+        .entries()
+        .iter()
+        .map(|(scores, (_, colors))| (scores.clone(), colors.clone()))
+        .collect_vec();
+    save_scatter("pareto.svg", &names, &points).unwrap();
+}
+
+fn parse_weights(value: &str) -> [f32; 5] {
+    let weights = value
+        .split(',')
+        .map(|w| w.parse::<f32>().expect("Invalid weight"))
+        .collect_vec();
+    let weights: [f32; 5] = weights.try_into().expect("Need exactly 5 weights");
+    assert!(weights.iter().all(|w| *w > 0.0), "Weights must be positive");
+    weights
+}
+
+// Estimates the best score each metric can reach on its own, using short pilot runs.
+// Weighting every metric by its achievable score times its relative importance puts them all on the same scale:
+// a weighted score of 0.8 means the metric reaches 80% of what it could on its own.
+// cargo run -p palette-optimizer --release -- calibrate --relative 1,1,1,0.8,1.2
+fn calibrate(args: &[String]) {
+    let mut relative = [1.0; 5];
+    let mut num_iter: u64 = 20000000;
+    let mut restarts = 2;
+    let mut n = 8;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().expect("Missing option value");
+        match arg.as_str() {
+            "--relative" => relative = parse_weights(value),
+            "--iter" => num_iter = value.parse().expect("Invalid iteration count"),
+            "--restarts" => restarts = value.parse().expect("Invalid number of restarts"),
+            "--size" => n = value.parse().expect("Invalid palette size"),
+            _ => panic!("Unknown option {}", arg),
+        }
+    }
+
+    let luts = Luts::new();
+    let names = PAIR_METRIC_NAMES
+        .iter()
+        .chain(CONSTRAINT_NAMES.iter())
+        .collect_vec();
+    let mut weights = [0.0; 5];
+    println!("metric\tachievable\trelative\tweight");
+    for k in 0..weights.len() {
+        let achievable = (0..restarts)
+            .map(|_| {
+                let job = Job::new(&luts, n)
+                    .only_metric(k)
+                    .schedule(Schedule::new(num_iter));
+                run_job(&job, job.initial_colors()).score
+            })
+            .fold(f32::NEG_INFINITY, f32::max);
+        weights[k] = achievable * relative[k];
+        println!(
+            "{}\t{:.3}\t{}\t{:.3}",
+            names[k], achievable, relative[k], weights[k]
+        );
+    }
+    println!(
+        "--weights {}",
+        weights.iter().map(|w| format!("{:.3}", w)).join(",")
+    );
+}
+
+// Reads a whitespace-separated square matrix with one row per line.
+fn read_importance(path: &str) -> PairImportance {
+    let text = std::fs::read_to_string(path).expect("Could not read importance matrix");
+    let rows = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.split_whitespace()
+                .map(|w| w.parse::<f32>().expect("Invalid importance"))
+                .collect_vec()
+        })
+        .collect_vec();
+    PairImportance::new(&rows)
+}
+
+// cargo run -p palette-optimizer --release -- --importance importance.txt --soft-min 0.05 --weights 25,20,20,15,30 --log run.jsonl --seed 42
+// cargo run -p palette-optimizer --release -- --patience 50000000 --broad-patience 20000000 --time 60 --target 1.0
+// cargo run -p palette-optimizer --release -- --lightness 30,90 --max-chroma 25 --hue 0,120 --hue 200,300
+// cargo run -p palette-optimizer --release -- --init farthest --same-within 5
+// cargo run -p palette-optimizer --release -- --lut quantized,cached,cached,cached,dense
+// cargo run -p palette-optimizer --release -- --init FA35EC,9449FF,tomato,"rgb(59, 219, 120)"
+// cargo run -p palette-optimizer --release -- --continuous --iter 2000000 --seed 1
+// cargo run -p palette-optimizer --release -- --gamut p3
+// cargo run -p palette-optimizer --release -- --alpha 0.6 --backgrounds white,#121212
+// cargo run -p palette-optimizer --release -- --cvd-model machado --severity 0.6
+fn categorical(args: &[String]) {
+    let mut importance = PairImportance::uniform(8);
+    let mut objective = Objective::Min;
+    let mut weights = DEFAULT_WEIGHTS;
+    let mut log_path = None;
+    let mut seed = None;
+    let mut schedule = Schedule::new(NUM_ITER);
+    let mut bounds = OklchBounds::default();
+    let mut init = Init::Random;
+    let mut same_within = SAME_WITHIN;
+    let mut storage = None;
+    let mut continuous = false;
+    let mut gamut = Gamut::Srgb;
+    let mut cvd_model = CvdModel::Brettel1997;
+    let mut severity = 1.0;
+    let mut alpha = None;
+    let mut backgrounds = vec![sRGB([0x00, 0x00, 0x00]), sRGB([0xFF, 0xFF, 0xFF])];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--importance" => {
+                importance = read_importance(args.next().expect("Missing importance matrix"))
+            }
+            "--soft-min" => {
+                let value = args.next().expect("Missing soft-min temperature");
+                let t = value.parse::<f32>().expect("Invalid soft-min temperature");
+                assert!(t > 0.0, "Soft-min temperature must be positive");
+                objective = Objective::SoftMin(t);
+            }
+            "--weights" => weights = parse_weights(args.next().expect("Missing weights")),
+            "--log" => log_path = Some(args.next().expect("Missing log path").as_str()),
+            "--same-within" => {
+                let value = args.next().expect("Missing distance");
+                same_within = value.parse::<f32>().expect("Invalid distance");
+            }
+            "--lut" => storage = Some(parse_lut_storage(args.next().expect("Missing LUT storage"))),
+            "--init" => init = parse_init(args.next().expect("Missing init strategy")),
+            "--seed" => {
+                let value = args.next().expect("Missing seed");
+                seed = Some(value.parse::<u64>().expect("Invalid seed"));
+            }
+            "--continuous" => continuous = true,
+            "--gamut" => gamut = args.next().expect("Missing gamut").parse().unwrap(),
+            "--alpha" => {
+                let value = args.next().expect("Missing alpha");
+                alpha = Some(value.parse::<f32>().expect("Invalid alpha"));
+            }
+            "--backgrounds" => {
+                backgrounds = parse_colors(args.next().expect("Missing backgrounds"));
+            }
+            _ => {
+                let value = args.next().expect("Missing option value");
+                if !parse_schedule_option(&mut schedule, arg, value)
+                    && !parse_region_option(&mut bounds, arg, value)
+                    && !parse_cvd_option(&mut cvd_model, &mut severity, arg, value)
+                {
+                    panic!("Unknown option {}", arg)
+                }
+            }
+        }
+    }
+
+    // The continuous optimizer computes colors directly and only reads the LUTs for
+    // initialization and final scores, so dense tables aren't worth building.
+    let default_storage = if continuous {
+        LutStorage::Cached
+    } else {
+        LutStorage::Dense
+    };
+    let storage = storage.unwrap_or([default_storage; 5]);
+    let luts = Luts::for_cvd(storage, gamut, cvd_model, severity);
+    let unbounded =
+        bounds.lightness.is_none() && bounds.max_chroma.is_none() && bounds.hues.is_empty();
+    let region = (!unbounded).then(|| Region::for_gamut(bounds, gamut));
+    let mut job = Job::new(&luts, importance.len())
+        .importance(importance)
+        .objective(objective)
+        .weights(weights)
+        .schedule(schedule)
+        .init(init);
+    if let Some(region) = region.as_ref() {
+        job = job.region(region);
+    }
+    if continuous {
+        job = job.continuous();
+    }
+    if let Some(alpha) = alpha {
+        job = job.translucent(Translucency::new(alpha, backgrounds));
+    }
+    optimize(&job, seed, log_path, same_within);
+}
+
+#[derive(Serialize)]
+struct MetricReport {
+    name: &'static str,
+    score: f32,
+    weighted: f32,
+    // Indices into the palette of the colors that limit the score.
+    limiting: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct RunReport<'j> {
+    run: usize,
+    seed: Option<u64>,
+    config: &'j Job<'j>,
+    // The colors' 8-bit codes are in this gamut. See `srgb_fallback` for plain sRGB.
+    gamut: Gamut,
+    palette: Vec<sRGB>,
+    score: f32,
+    metrics: Vec<MetricReport>,
+    elapsed_secs: f64,
+    iterations: u64,
+    srgb_fallback: Option<FallbackReport>,
+}
+
+// The palette clipped to sRGB, for wide-gamut runs.
+#[derive(Serialize)]
+struct FallbackReport {
+    palette: Vec<sRGB>,
+    score: f32,
+    // Indices into the palette of the colors that had to be clipped.
+    out_of_gamut: Vec<usize>,
+}
+
+fn make_run_report<'j>(
+    run: usize,
+    job: &'j Job,
+    result: &JobResult,
+    fallback: Option<FallbackReport>,
+) -> RunReport<'j> {
+    RunReport {
+        run: run,
+        seed: result.seed,
+        config: job,
+        gamut: job.gamut(),
+        palette: result.palette.clone(),
+        score: result.score,
+        metrics: result
+            .metrics
+            .iter()
+            .map(|m| MetricReport {
+                name: m.name,
+                score: m.score,
+                weighted: m.weighted,
+                limiting: match m.limiting {
+                    Metric::Pair(_, (a, b)) => vec![a, b],
+                    Metric::Const(_, j) => vec![j],
+                },
+            })
+            .collect_vec(),
+        elapsed_secs: result.elapsed.as_secs_f64(),
+        iterations: result.iterations,
+        srgb_fallback: fallback,
+    }
+}
+
+// Runs a few independent restarts of `job`, each from fresh random colors.
+// Restart k uses `seed + k` if a seed is given, so the whole set can be reproduced.
+// Every restart's result is written to result_XX.json, next to its img_XX.svg.
+// If `log_path` is given, every run's progress is logged to it as JSON lines.
+// Afterwards, reports which restarts found the same palette, up to order and `same_within`.
+fn optimize(job: &Job, seed: Option<u64>, log_path: Option<&str>, same_within: f32) {
+    let mut log = log_path.map(|path| BufWriter::new(File::create(path).unwrap()));
+    let mut palettes = vec![];
+    // Only used to score fallbacks, so cheap to build and slow to use.
+    let fallback_luts = (job.gamut() != Gamut::Srgb).then(|| {
+        Luts::for_cvd(
+            [LutStorage::Cached; 5],
+            Gamut::Srgb,
+            job.cvd_model(),
+            job.severity(),
+        )
+    });
+    // breakpoint();
+    for big_num in 0..4 {
+        let run_seed = match seed {
+            Some(s) => s.wrapping_add(big_num as u64),
+            None => rand::random(),
+        };
+        let result = run_with_progress(
+            job,
             big_num,
-            start_time.elapsed(),
-            best.0,
-            best.1.iter().map(to_string).collect_vec()
+            log.as_mut().map(|l| l as &mut dyn Write),
+            |observer| job.run_seeded(run_seed, observer),
+        );
+        // Wide-gamut codes are labeled, since they aren't sRGB hex colors.
+        let label = match job.gamut() {
+            Gamut::Srgb => String::new(),
+            gamut => format!("{} ", gamut.name()),
+        };
+        println!(
+            "{}:\t{:#?}\t{}\t{}\t{}{:?}",
+            big_num,
+            result.elapsed,
+            result.iterations,
+            result.score,
+            label,
+            result.palette.iter().map(sRGB::to_string).collect_vec()
         );
         // 's/[\[" #]//g'
         // https://www.atatus.com/tools/color-code-viewer#
 
-        save_svg(format!("img_{:02}.svg", big_num), best.1).unwrap();
+        let fallback = fallback_luts.as_ref().map(|srgb_luts| {
+            let fallback = job.srgb_fallback(&result.palette, srgb_luts);
+            let out_of_gamut = result
+                .palette
+                .iter()
+                .positions(|c| {
+                    !RgbColor::from_8bit(job.gamut(), *c)
+                        .convert(Gamut::Srgb)
+                        .in_gamut()
+                })
+                .collect_vec();
+            println!(
+                "\tsRGB fallback:\t{}\t{:?}\t{} clipped",
+                fallback.score,
+                fallback.palette.iter().map(sRGB::to_string).collect_vec(),
+                out_of_gamut.len()
+            );
+            FallbackReport {
+                palette: fallback.palette.clone(),
+                score: fallback.score,
+                out_of_gamut: out_of_gamut,
+            }
+        });
+        // SVG fills are sRGB, so wide-gamut palettes are drawn with their fallback.
+        let svg_palette = match &fallback {
+            Some(fallback) => fallback.palette.clone(),
+            None => result.palette.clone(),
+        };
+        let report = make_run_report(big_num, job, &result, fallback);
+        let json = serde_json::to_string_pretty(&report).unwrap();
+        std::fs::write(format!("result_{:02}.json", big_num), json).unwrap();
+        palettes.push(result.palette.clone());
+        save_svg(format!("img_{:02}.svg", big_num), svg_palette).unwrap();
+    }
+
+    let groups = group_similar(&palettes, job.gamut(), same_within);
+    for group in groups.iter().filter(|g| g.len() > 1) {
+        println!(
+            "Restarts {} converged to the same palette.",
+            group.iter().join(", ")
+        );
     }
+    println!(
+        "{} distinct palettes from {} restarts.",
+        groups.len(),
+        palettes.len()
+    );
 
     // breakpoint();
 }
+
+fn main() {
+    let args = env::args().collect_vec();
+    match args.get(1).map(String::as_str) {
+        Some("score") => score(&args[2..]),
+        Some("extend") => extend(&args[2..]),
+        Some("largest") => largest(&args[2..]),
+        Some("sequential") => sequential(&args[2..]),
+        Some("diverging") => diverging(&args[2..]),
+        Some("pareto") => pareto(&args[2..]),
+        Some("calibrate") => calibrate(&args[2..]),
+        _ => categorical(&args[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_colors() {
+        assert_eq!(
+            parse_colors("FA35EC,rgb(59, 219, 120), tomato,#121212"),
+            vec![
+                sRGB([0xFA, 0x35, 0xEC]),
+                sRGB([59, 219, 120]),
+                sRGB([0xFF, 0x63, 0x47]),
+                sRGB([0x12, 0x12, 0x12]),
+            ]
+        );
+    }
+}
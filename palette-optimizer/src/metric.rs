@@ -1,60 +1,190 @@
 use crate::score::*;
 use itertools::Itertools;
 
-use color_lib::{as_index, sRGB, HyAB, Oklab};
-use itertools::iproduct;
+use crate::lut::Lut;
+use color_lib::{sRGB, HyAB, Oklab, Rgba};
+use serde::Serialize;
 
-pub struct SrgbLut<T> {
-    data: Vec<T>,
+pub trait ScoreMetric<T: ScoreIndex> {
+    fn get_min_score(&self) -> (f32, T);
+
+    fn get_scores(&self) -> &[(f32, T)];
+
+    fn update(&mut self, updated_index: usize, updated_color: &sRGB);
+
+    fn test_improvement(&self, updated_index: usize, updated_color: &sRGB) -> bool;
 }
 
-impl<T: Copy> SrgbLut<T> {
-    pub fn new(f: impl Fn(sRGB) -> T) -> Self {
-        let mut data = Vec::with_capacity(1 << 24);
-        // I wish there was an easy way to allow this to be parallel,
-        // But it is fast enough that it isn't a significant issue.
-        for (r, g, b) in iproduct!(0x00..=0xFF, 0x00..=0xFF, 0x00..=0xFF) {
-            let c = [r, g, b];
-            data.push(f(c))
+// Every kind of pair metric, so the optimizer's loop calls them without dynamic dispatch.
+pub enum PairMetric<'a> {
+    Distance(PairDistance<'a>),
+    Composited1(PairDistance<'a, [Oklab; 1]>),
+    Composited2(PairDistance<'a, [Oklab; 2]>),
+    Composited3(PairDistance<'a, [Oklab; 3]>),
+    Composited4(PairDistance<'a, [Oklab; 4]>),
+    Adjacent(AdjacentDistance<'a>),
+}
+
+// Evaluates `$body` with `$m` bound to whichever metric `$metric` holds.
+macro_rules! with_pair_metric {
+    ($metric:expr, $m:ident => $body:expr) => {
+        match $metric {
+            PairMetric::Distance($m) => $body,
+            PairMetric::Composited1($m) => $body,
+            PairMetric::Composited2($m) => $body,
+            PairMetric::Composited3($m) => $body,
+            PairMetric::Composited4($m) => $body,
+            PairMetric::Adjacent($m) => $body,
         }
-        Self { data: data }
+    };
+}
+
+impl<'a> ScoreMetric<(usize, usize)> for PairMetric<'a> {
+    #[inline]
+    fn get_min_score(&self) -> (f32, (usize, usize)) {
+        with_pair_metric!(self, m => m.get_min_score())
+    }
+
+    #[inline]
+    fn get_scores(&self) -> &[(f32, (usize, usize))] {
+        with_pair_metric!(self, m => m.get_scores())
+    }
+
+    #[inline]
+    fn update(&mut self, updated_index: usize, updated_color: &sRGB) {
+        with_pair_metric!(self, m => m.update(updated_index, updated_color))
     }
 
-    pub fn get(&self, c: &sRGB) -> T {
-        self.data[as_index(c)]
+    #[inline]
+    fn test_improvement(&self, updated_index: usize, updated_color: &sRGB) -> bool {
+        with_pair_metric!(self, m => m.test_improvement(updated_index, updated_color))
     }
 }
 
-impl SrgbLut<f32> {
-    pub fn new_constraint<T2>(backgrounds: &Vec<T2>, f: impl Fn(&T2, &sRGB) -> f32) -> Self {
-        Self::new(|c| {
-            backgrounds
-                .iter()
-                .map(|bg| f(bg, &c))
-                .min_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap()
-        })
+// Colors drawn with `alpha` opacity over each of `backgrounds`, like translucent fills over light and dark surfaces.
+#[derive(Clone, Debug, Serialize)]
+pub struct Translucency {
+    pub alpha: f32,
+    pub backgrounds: Vec<sRGB>,
+}
+
+impl Translucency {
+    pub fn new(alpha: f32, backgrounds: Vec<sRGB>) -> Self {
+        assert!(!backgrounds.is_empty(), "Need at least one background");
+        Self {
+            alpha: alpha,
+            backgrounds: backgrounds,
+        }
+    }
+
+    // `c` as seen over each background, along with that background.
+    pub fn composite<'t>(&'t self, c: &sRGB) -> impl Iterator<Item = (sRGB, &'t sRGB)> + 't {
+        let c = Rgba::new(*c, self.alpha);
+        self.backgrounds.iter().map(move |bg| (c.over(bg), bg))
+    }
+
+    // Kept out of line, since inlining it into `Constraint` slows down opaque constraints too.
+    #[inline(never)]
+    pub fn worst_contrast(&self, c: &sRGB, contrast: fn(&sRGB, &sRGB) -> f32) -> f32 {
+        self.composite(c)
+            .map(|(seen, bg)| contrast(&seen, bg))
+            .fold(f32::INFINITY, f32::min)
     }
 }
 
-pub trait ScoreMetric<T: ScoreIndex> {
-    fn get_min_score(&self) -> (f32, T);
+// How a pair metric sees each color: as one Oklab color, or as one per background if translucent.
+pub trait PairColor: Copy {
+    fn see(lut: &Lut<Oklab>, translucency: Option<&Translucency>, c: &sRGB) -> Self;
 
-    fn update(&mut self, updated_index: usize, updated_color: &sRGB);
+    fn distance(&self, other: &Self) -> f32;
+}
 
-    fn test_improvement(&self, updated_index: usize, updated_color: &sRGB) -> bool;
+impl PairColor for Oklab {
+    fn see(lut: &Lut<Oklab>, _translucency: Option<&Translucency>, c: &sRGB) -> Self {
+        lut.get(c)
+    }
+
+    fn distance(&self, other: &Self) -> f32 {
+        HyAB(self, other)
+    }
+}
+
+impl<const N: usize> PairColor for [Oklab; N] {
+    fn see(lut: &Lut<Oklab>, translucency: Option<&Translucency>, c: &sRGB) -> Self {
+        let translucency = translucency.expect("Translucent colors need backgrounds");
+        let mut views = [Oklab::default(); N];
+        for (view, (seen, _)) in views.iter_mut().zip(translucency.composite(c)) {
+            *view = lut.get(&seen);
+        }
+        views
+    }
+
+    // Only as far apart as over the background where they are closest.
+    fn distance(&self, other: &Self) -> f32 {
+        self.iter()
+            .zip(other)
+            .map(|(c1, c2)| HyAB(c1, c2))
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+enum ConstraintValue<'a> {
+    Lut(&'a Lut<f32>),
+    // The worst contrast of each color composited over a background, against that background.
+    Composited(Translucency, fn(&sRGB, &sRGB) -> f32),
+}
+
+impl ConstraintValue<'_> {
+    fn get(&self, c: &sRGB) -> f32 {
+        match self {
+            ConstraintValue::Lut(lut) => lut.get(c),
+            ConstraintValue::Composited(translucency, contrast) => {
+                translucency.worst_contrast(c, *contrast)
+            }
+        }
+    }
 }
 
 pub struct Constraint<'a> {
-    constraint_lut: &'a SrgbLut<f32>,
+    value: ConstraintValue<'a>,
     scores: Scores<usize>,
 }
 
 impl<'a> Constraint<'a> {
-    pub fn new(colors: &Vec<sRGB>, constraint_lut: &'a SrgbLut<f32>) -> Self {
-        let data = colors.iter().map(|c| constraint_lut.get(c)).collect_vec();
+    // The first `fixed` colors never change, so they are never the limiting index.
+    pub fn new(colors: &Vec<sRGB>, fixed: usize, constraint_lut: &'a Lut<f32>) -> Self {
+        Self::with_value(colors, fixed, ConstraintValue::Lut(constraint_lut))
+    }
+
+    // Scores translucent colors by `contrast(composited, background)` over their worst background.
+    // This has no lookup table, so it is slower than `new`.
+    pub fn composited(
+        colors: &Vec<sRGB>,
+        fixed: usize,
+        translucency: Translucency,
+        contrast: fn(&sRGB, &sRGB) -> f32,
+    ) -> Self {
+        Self::with_value(
+            colors,
+            fixed,
+            ConstraintValue::Composited(translucency, contrast),
+        )
+    }
+
+    fn with_value(colors: &Vec<sRGB>, fixed: usize, value: ConstraintValue<'a>) -> Self {
+        let data = colors
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                if i < fixed {
+                    f32::INFINITY
+                } else {
+                    value.get(c)
+                }
+            })
+            .collect_vec();
         Constraint {
-            constraint_lut: constraint_lut,
+            value: value,
             scores: Scores::new(&data),
         }
     }
@@ -65,56 +195,138 @@ impl<'a> ScoreMetric<usize> for Constraint<'a> {
         self.scores.get_min_score()
     }
 
+    fn get_scores(&self) -> &[(f32, usize)] {
+        self.scores.get_scores()
+    }
+
     fn update(&mut self, updated_index: usize, updated_color: &sRGB) {
         self.scores
-            .update(updated_index, self.constraint_lut.get(updated_color));
+            .update(updated_index, self.value.get(updated_color));
     }
 
     fn test_improvement(&self, _updated_index: usize, updated_color: &sRGB) -> bool {
-        self.constraint_lut.get(updated_color) > self.scores.get_min_score().0
+        self.value.get(updated_color) > self.scores.get_min_score().0
     }
 }
 
-pub struct PairDistance<'a> {
-    color_lut: &'a SrgbLut<Oklab>,
-    pre_colors: Vec<Oklab>,
+pub struct PairDistance<'a, C: PairColor = Oklab> {
+    color_lut: &'a Lut<Oklab>,
+    translucency: Option<Translucency>,
+    fixed: usize,
+    importance: PairImportance,
+    pre_colors: Vec<C>,
     pre_scores: Vec<(f32, usize)>,
     scores: Scores<(usize, usize)>,
 }
 
 impl<'a> PairDistance<'a> {
-    pub fn new(colors: &Vec<sRGB>, color_lut: &'a SrgbLut<Oklab>) -> Self {
-        let pre_colors = colors.iter().map(|c| color_lut.get(c)).collect_vec();
-        let pre_scores = get_pair_scores(&pre_colors);
+    // The first `fixed` colors never change, so pairs between them are ignored.
+    pub fn new(
+        colors: &Vec<sRGB>,
+        fixed: usize,
+        importance: PairImportance,
+        color_lut: &'a Lut<Oklab>,
+    ) -> Self {
+        Self::with_translucency(colors, fixed, importance, color_lut, None)
+    }
+}
+
+impl<'a, const N: usize> PairDistance<'a, [Oklab; N]> {
+    // Compares translucent colors as seen over each of the `N` backgrounds, scoring a pair by the worst one.
+    pub fn composited(
+        colors: &Vec<sRGB>,
+        fixed: usize,
+        importance: PairImportance,
+        color_lut: &'a Lut<Oklab>,
+        translucency: Translucency,
+    ) -> Self {
+        assert_eq!(
+            translucency.backgrounds.len(),
+            N,
+            "Wrong number of backgrounds"
+        );
+        Self::with_translucency(colors, fixed, importance, color_lut, Some(translucency))
+    }
+}
+
+// `PairDistance::composited` for however many backgrounds `translucency` has, up to four.
+pub fn composited_distance<'a>(
+    colors: &Vec<sRGB>,
+    fixed: usize,
+    importance: PairImportance,
+    color_lut: &'a Lut<Oklab>,
+    translucency: Translucency,
+) -> PairMetric<'a> {
+    let (c, f, i, l, t) = (colors, fixed, importance, color_lut, translucency);
+    match t.backgrounds.len() {
+        1 => PairMetric::Composited1(PairDistance::composited(c, f, i, l, t)),
+        2 => PairMetric::Composited2(PairDistance::composited(c, f, i, l, t)),
+        3 => PairMetric::Composited3(PairDistance::composited(c, f, i, l, t)),
+        4 => PairMetric::Composited4(PairDistance::composited(c, f, i, l, t)),
+        n => panic!("At most 4 backgrounds are supported, got {}", n),
+    }
+}
+
+impl<'a, C: PairColor> PairDistance<'a, C> {
+    fn with_translucency(
+        colors: &Vec<sRGB>,
+        fixed: usize,
+        importance: PairImportance,
+        color_lut: &'a Lut<Oklab>,
+        translucency: Option<Translucency>,
+    ) -> Self {
+        assert!(fixed < colors.len());
+        assert_eq!(importance.len(), colors.len());
+        let pre_colors = colors
+            .iter()
+            .map(|c| C::see(color_lut, translucency.as_ref(), c))
+            .collect_vec();
+        let pre_scores = get_pair_scores(&pre_colors, fixed, &importance);
         let scores = Scores::new_pairs(&pre_scores);
         Self {
             color_lut: color_lut,
+            translucency: translucency,
+            fixed: fixed,
+            importance: importance,
             pre_colors: pre_colors,
             pre_scores: pre_scores,
             scores: scores,
         }
     }
 
+    fn see(&self, c: &sRGB) -> C {
+        C::see(self.color_lut, self.translucency.as_ref(), c)
+    }
+
     fn update_pair_score(&mut self, i: usize) {
-        let (val, ind) = get_pair_score(i, &self.pre_colors);
+        let (val, ind) = get_pair_score(i, &self.pre_colors, self.fixed, &self.importance);
         self.pre_scores[i] = (val, ind);
         self.scores.update((i, ind), val);
     }
 }
 
-impl<'a> ScoreMetric<(usize, usize)> for PairDistance<'a> {
+impl<'a, C: PairColor> ScoreMetric<(usize, usize)> for PairDistance<'a, C> {
     fn get_min_score(&self) -> (f32, (usize, usize)) {
         self.scores.get_min_score()
     }
 
+    fn get_scores(&self) -> &[(f32, (usize, usize))] {
+        self.scores.get_scores()
+    }
+
     fn update(&mut self, updated_index: usize, updated_color: &sRGB) {
-        let new_color = self.color_lut.get(updated_color);
+        debug_assert!(updated_index >= self.fixed);
+        let new_color = self.see(updated_color);
         self.pre_colors[updated_index] = new_color;
 
         // Recompute scores of indexes before updated_index
         for i in 0..updated_index {
             let (prev_score, prev_index) = self.pre_scores[i];
-            let score = HyAB(&new_color, &self.pre_colors[i]);
+            let score = self.importance.pair_score(
+                i,
+                updated_index,
+                new_color.distance(&self.pre_colors[i]),
+            );
             if score < prev_score {
                 self.pre_scores[i] = (score, updated_index);
                 self.scores.update((i, updated_index), score)
@@ -131,13 +343,139 @@ impl<'a> ScoreMetric<(usize, usize)> for PairDistance<'a> {
     }
 
     fn test_improvement(&self, updated_index: usize, updated_color: &sRGB) -> bool {
-        let new_color = self.color_lut.get(updated_color);
+        let new_color = self.see(updated_color);
         let (old_score, (i, j)) = self.scores.get_min_score();
         let color = if updated_index == i {
             self.pre_colors[j]
         } else {
             self.pre_colors[i]
         };
-        return HyAB(&new_color, &color) > old_score;
+        return self.importance.pair_score(i, j, new_color.distance(&color)) > old_score;
+    }
+}
+
+// Scores only the steps between neighboring colors, for ordered (sequential and diverging) palettes.
+// Lightness goes up to the color at `midpoint`, and back down after it. Diverging palettes also score
+// each color against its mirror on the other ramp; see `get_step_score`.
+pub struct AdjacentDistance<'a> {
+    color_lut: &'a Lut<Oklab>,
+    midpoint: usize,
+    pre_colors: Vec<Oklab>,
+    scores: Scores<(usize, usize)>,
+}
+
+impl<'a> AdjacentDistance<'a> {
+    // Dark to light.
+    pub fn new(colors: &Vec<sRGB>, color_lut: &'a Lut<Oklab>) -> Self {
+        Self::with_midpoint(colors, colors.len() - 1, color_lut)
+    }
+
+    // Dark to light to dark, with the lightest color in the middle.
+    pub fn diverging(colors: &Vec<sRGB>, color_lut: &'a Lut<Oklab>) -> Self {
+        Self::with_midpoint(colors, colors.len() / 2, color_lut)
+    }
+
+    fn with_midpoint(colors: &Vec<sRGB>, midpoint: usize, color_lut: &'a Lut<Oklab>) -> Self {
+        let pre_colors = colors.iter().map(|c| color_lut.get(c)).collect_vec();
+        let scores = Scores::new_pairs(&get_step_scores(&pre_colors, midpoint));
+        Self {
+            color_lut: color_lut,
+            midpoint: midpoint,
+            pre_colors: pre_colors,
+            scores: scores,
+        }
+    }
+
+    fn update_step_score(&mut self, i: usize) {
+        let (score, j) = get_step_score(i, &self.pre_colors, self.midpoint);
+        self.scores.update((i, j), score);
+    }
+}
+
+impl<'a> ScoreMetric<(usize, usize)> for AdjacentDistance<'a> {
+    fn get_min_score(&self) -> (f32, (usize, usize)) {
+        self.scores.get_min_score()
+    }
+
+    fn get_scores(&self) -> &[(f32, (usize, usize))] {
+        self.scores.get_scores()
+    }
+
+    fn update(&mut self, updated_index: usize, updated_color: &sRGB) {
+        let n = self.pre_colors.len();
+        self.pre_colors[updated_index] = self.color_lut.get(updated_color);
+        for i in updated_index.saturating_sub(1)..(updated_index + 1).min(n - 1) {
+            self.update_step_score(i);
+        }
+        // The slot holding this color's mirror.
+        let mirror = n - 1 - updated_index;
+        if updated_index > self.midpoint && mirror < self.midpoint {
+            self.update_step_score(mirror);
+        }
+    }
+
+    fn test_improvement(&self, updated_index: usize, updated_color: &sRGB) -> bool {
+        let (old_score, (i, j)) = self.scores.get_min_score();
+        let new_color = self.color_lut.get(updated_color);
+        let score = if updated_index == i {
+            ordered_pair_score(&new_color, &self.pre_colors[j], i, j, self.midpoint)
+        } else {
+            ordered_pair_score(&self.pre_colors[i], &new_color, i, j, self.midpoint)
+        };
+        return score > old_score;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lut::{make_lut, LutStorage};
+    use color_lib::APCA;
+
+    #[test]
+    fn test_composited() {
+        let lut = make_lut(LutStorage::Cached, Oklab::from);
+        let colors = vec![
+            sRGB([0xFA, 0x35, 0xEC]),
+            sRGB([0x94, 0x49, 0xFF]),
+            sRGB([0x3B, 0xDB, 0x78]),
+        ];
+        let backgrounds = vec![sRGB([0xFF, 0xFF, 0xFF]), sRGB([0x12, 0x12, 0x12])];
+        let importance = PairImportance::uniform(3);
+        let opaque = PairDistance::new(&colors, 0, importance.clone(), &lut);
+        let distance = |alpha| {
+            let t = Translucency::new(alpha, backgrounds.clone());
+            composited_distance(&colors, 0, importance.clone(), &lut, t).get_min_score()
+        };
+        assert_eq!(distance(1.0), opaque.get_min_score());
+        // Seen through, the colors take on more of the same background.
+        assert!(distance(0.6).0 < opaque.get_min_score().0);
+
+        let contrast = |alpha| {
+            let t = Translucency::new(alpha, backgrounds.clone());
+            Constraint::composited(&colors, 0, t, APCA)
+                .get_min_score()
+                .0
+        };
+        let opaque_contrast = colors
+            .iter()
+            .flat_map(|c| backgrounds.iter().map(|bg| APCA(c, bg)))
+            .fold(f32::INFINITY, f32::min);
+        assert_eq!(contrast(1.0), opaque_contrast);
+        assert!(contrast(0.6) < opaque_contrast);
+    }
+
+    #[test]
+    fn test_diverging_updates() {
+        let lut = make_lut(LutStorage::Cached, Oklab::from);
+        let mut colors = (0..7).map(|_| sRGB(rand::random())).collect_vec();
+        let mut metric = AdjacentDistance::diverging(&colors, &lut);
+        for _ in 0..1000 {
+            let i = rand::random::<usize>() % colors.len();
+            colors[i] = sRGB(rand::random());
+            metric.update(i, &colors[i]);
+            let fresh = AdjacentDistance::diverging(&colors, &lut);
+            assert_eq!(metric.get_min_score().0, fresh.get_min_score().0);
+        }
     }
 }
@@ -1,26 +1,109 @@
-use crate::metric::{Constraint, PairDistance, ScoreMetric};
+use crate::metric::{Constraint, PairMetric, ScoreMetric};
+use crate::region::Region;
 use crate::update::{update_color, update_color_pair};
 use color_lib::sRGB;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+use std::time::{Duration, Instant};
 
-#[derive(Clone, Copy)]
-enum Metric {
+#[derive(Clone, Copy, Debug)]
+pub enum Metric {
     Pair(usize, (usize, usize)),
     Const(usize, usize),
 }
 
+#[derive(Clone, Copy, Debug, Serialize)]
+pub enum Objective {
+    // Maximize the single worst weighted score.
+    Min,
+    // Maximize a log-sum-exp soft minimum of every weighted score, with the given temperature.
+    // Colors to move are picked in proportion to how much they contribute to the soft minimum,
+    // so the optimizer keeps improving the other scores while the worst one is stuck.
+    // As the temperature approaches 0, this approaches `Min`.
+    SoftMin(f32),
+}
+
+// The state of an optimizer partway through a run.
+pub struct Snapshot<'s> {
+    pub iteration: u64,
+    pub colors: &'s Vec<sRGB>,
+    // Objective score of the current colors.
+    pub score: f32,
+    pub best_score: f32,
+    // The worst weighted score of the current colors, and where it comes from.
+    pub limiting_score: f32,
+    pub limiting: Metric,
+}
+
+pub enum Control {
+    Continue,
+    Stop,
+}
+
+pub trait Observer {
+    // Number of iterations between snapshots. Zero counts as one.
+    fn interval(&self) -> u64 {
+        1000000
+    }
+
+    fn observe(&mut self, snapshot: &Snapshot) -> Control;
+}
+
+impl Observer for () {
+    fn observe(&mut self, _snapshot: &Snapshot) -> Control {
+        Control::Continue
+    }
+}
+
+// Checking the clock every iteration would be noticeably slow.
+pub(crate) const TIME_CHECK_INTERVAL: u64 = 4096;
+
+// When to stop a run, and when to switch from the broad phase to the narrow phase.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Schedule {
+    pub max_iter: u64,
+    // Stop once the best score hasn't improved for this many iterations.
+    pub patience: Option<u64>,
+    pub time_limit: Option<Duration>,
+    pub target_score: Option<f32>,
+    // Switch to the narrow phase once the best score hasn't improved for this many iterations,
+    // or after half of `max_iter`, whichever comes first.
+    pub broad_patience: Option<u64>,
+}
+
+impl Schedule {
+    pub fn new(max_iter: u64) -> Self {
+        Self {
+            max_iter: max_iter,
+            patience: None,
+            time_limit: None,
+            target_score: None,
+            broad_patience: None,
+        }
+    }
+}
+
 pub struct Optimizer<'a> {
     colors: Vec<sRGB>,
-    min_score_metric: Metric,
-    pair_metrics: Vec<(f32, PairDistance<'a>)>,
+    fixed: usize,
+    objective: Objective,
+    score: f32,
+    min_score: (f32, Metric),
+    pair_metrics: Vec<(f32, PairMetric<'a>)>,
     constraints: Vec<(f32, Constraint<'a>)>,
     best_colors: (f32, Vec<sRGB>),
+    region: Option<&'a Region>,
+    rng: StdRng,
 }
 
 impl<'a> Optimizer<'a> {
+    // Only colors from index `fixed` onwards are optimized.
+    // The metrics must have been constructed with the same `fixed`.
     pub fn new(
-        pair_metrics: Vec<(f32, PairDistance<'a>)>,
+        pair_metrics: Vec<(f32, PairMetric<'a>)>,
         constraints: Vec<(f32, Constraint<'a>)>,
         colors: Vec<sRGB>,
+        fixed: usize,
     ) -> Self {
         let best_colors = colors.clone();
         let min_score = pair_metrics
@@ -38,29 +121,116 @@ impl<'a> Optimizer<'a> {
             .unwrap();
         Self {
             colors: colors,
-            min_score_metric: min_score.1,
+            fixed: fixed,
+            objective: Objective::Min,
+            score: min_score.0,
+            min_score: min_score,
             pair_metrics: pair_metrics,
             constraints: constraints,
             best_colors: (min_score.0, best_colors),
+            region: None,
+            rng: StdRng::from_entropy(),
         }
     }
 
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self.score = self.get_objective_score(self.min_score.0);
+        self.best_colors.0 = self.score;
+        self
+    }
+
+    // Keeps every color to be optimized inside `region`, which they must start in.
+    pub fn with_region(mut self, region: &'a Region) -> Self {
+        assert!(
+            self.colors[self.fixed..].iter().all(|c| region.contains(c)),
+            "Colors don't start inside the region"
+        );
+        self.region = Some(region);
+        self
+    }
+
+    // Runs with the same seed and starting colors make the same moves.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    fn weighted_scores(&self) -> impl Iterator<Item = (f32, Metric)> + '_ {
+        self.pair_metrics
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (w, pm))| {
+                pm.get_scores()
+                    .iter()
+                    .map(move |(s, pair_index)| (s / w, Metric::Pair(i, *pair_index)))
+            })
+            .chain(self.constraints.iter().enumerate().flat_map(|(i, (w, c))| {
+                c.get_scores()
+                    .iter()
+                    .map(move |(s, j)| (s / w, Metric::Const(i, *j)))
+            }))
+    }
+
+    // Scores are shifted by the minimum to keep the exponentials in range.
+    fn get_objective_score(&self, min_score: f32) -> f32 {
+        match self.objective {
+            Objective::Min => min_score,
+            Objective::SoftMin(_) if !min_score.is_finite() => min_score,
+            Objective::SoftMin(t) => {
+                let total: f32 = self
+                    .weighted_scores()
+                    .map(|(s, _)| (-(s - min_score) / t).exp())
+                    .sum();
+                min_score - t * total.ln()
+            }
+        }
+    }
+
+    fn sample_metric(&mut self, t: f32, min_score: f32) -> Metric {
+        let total: f32 = self
+            .weighted_scores()
+            .map(|(s, _)| (-(s - min_score) / t).exp())
+            .sum();
+        let mut r = self.rng.gen::<f32>() * total;
+        for (s, m) in self.weighted_scores() {
+            r -= (-(s - min_score) / t).exp();
+            if r <= 0.0 {
+                return m;
+            }
+        }
+        self.min_score.1
+    }
+
     pub fn update(&mut self) {
-        let m = self.min_score_metric;
+        let m = match self.objective {
+            Objective::Min => self.min_score.1,
+            Objective::SoftMin(t) => self.sample_metric(t, self.min_score.0),
+        };
         let (index, c) = match m {
+            Metric::Pair(i, pair) if pair.0 < self.fixed => {
+                // Only the second color of the pair is allowed to move.
+                let pair_metric = &self.pair_metrics[i].1;
+                let mut c = update_color(&self.colors, pair.1, self.region, &mut self.rng);
+                if !pair_metric.test_improvement(pair.1, &c) {
+                    c = update_color(&self.colors, pair.1, self.region, &mut self.rng)
+                }
+                (pair.1, c)
+            }
             Metric::Pair(i, pair) => {
                 let pair_metric = &self.pair_metrics[i].1;
-                let (mut index, mut c) = update_color_pair(&self.colors, pair);
+                let (mut index, mut c) =
+                    update_color_pair(&self.colors, pair, self.region, &mut self.rng);
                 if !pair_metric.test_improvement(index, &c) {
-                    (index, c) = update_color_pair(&self.colors, pair)
+                    (index, c) = update_color_pair(&self.colors, pair, self.region, &mut self.rng)
                 }
                 (index, c)
             }
             Metric::Const(i, index) => {
                 let constraint = &self.constraints[i].1;
-                let mut c = update_color(&self.colors, index);
+                let mut c = update_color(&self.colors, index, self.region, &mut self.rng);
                 if !constraint.test_improvement(index, &c) {
-                    c = update_color(&self.colors, index)
+                    c = update_color(&self.colors, index, self.region, &mut self.rng)
                 }
                 (index, c)
             }
@@ -83,9 +253,10 @@ impl<'a> Optimizer<'a> {
                 min_score = (score, Metric::Const(i, j));
             }
         }
-        self.min_score_metric = min_score.1;
-        if min_score.0 > self.best_colors.0 {
-            self.best_colors = (min_score.0, self.colors.clone());
+        self.min_score = min_score;
+        self.score = self.get_objective_score(min_score.0);
+        if self.score > self.best_colors.0 {
+            self.best_colors = (self.score, self.colors.clone());
         }
     }
 
@@ -93,7 +264,7 @@ impl<'a> Optimizer<'a> {
         self.colors = self.best_colors.1.clone();
         let mut min_score = (f32::INFINITY, Metric::Const(0, 0));
         for (i, (w, pair_metric)) in self.pair_metrics.iter_mut().enumerate() {
-            for index in 0..self.colors.len() {
+            for index in self.fixed..self.colors.len() {
                 pair_metric.update(index, &self.colors[index]);
             }
             let (s, pair_index) = pair_metric.get_min_score();
@@ -103,7 +274,7 @@ impl<'a> Optimizer<'a> {
             }
         }
         for (i, (w, constraint)) in self.constraints.iter_mut().enumerate() {
-            for index in 0..self.colors.len() {
+            for index in self.fixed..self.colors.len() {
                 constraint.update(index, &self.colors[index]);
             }
             let (s, j) = constraint.get_min_score();
@@ -112,8 +283,102 @@ impl<'a> Optimizer<'a> {
                 min_score = (score, Metric::Const(i, j));
             }
         }
-        self.min_score_metric = min_score.1;
-        assert_eq!(min_score.0, self.best_colors.0);
+        self.min_score = min_score;
+        self.score = self.get_objective_score(min_score.0);
+        match self.objective {
+            Objective::Min => assert_eq!(self.score, self.best_colors.0),
+            // The soft minimum is summed in heap order, which may differ after restoring.
+            Objective::SoftMin(_) => {
+                assert!((self.score - self.best_colors.0).abs() <= 1e-4 * self.score.abs().max(1.0))
+            }
+        }
+    }
+
+    // Runs until the schedule or the observer stops it, and returns how many iterations were run.
+    // The broad phase explores freely. The narrow phase returns to the best colors
+    // whenever they haven't improved for a while.
+    pub fn run(&mut self, schedule: &Schedule, observer: &mut dyn Observer) -> u64 {
+        let broad_iter = schedule.max_iter / 2;
+        let small_iter: u64 = 10000;
+        let interval = observer.interval().max(1);
+        let start_time = Instant::now();
+
+        let mut narrow = false;
+        let mut counter = 0;
+        let mut last_improvement = 0;
+        let mut best_score = self.get_best_score();
+        if schedule.target_score.is_some_and(|t| best_score >= t) {
+            return 0;
+        }
+        // Iterations are counted from 1, so `it` is how many have been run so far.
+        for it in 1..=schedule.max_iter {
+            self.update();
+            let improved = self.get_best_score() > best_score;
+            if improved {
+                best_score = self.get_best_score();
+                last_improvement = it;
+                if schedule.target_score.is_some_and(|t| best_score >= t) {
+                    return it;
+                }
+            }
+            let stalled = it - last_improvement;
+            if schedule.patience.is_some_and(|p| stalled >= p) {
+                return it;
+            }
+            if it % TIME_CHECK_INTERVAL == 0
+                && schedule
+                    .time_limit
+                    .is_some_and(|t| start_time.elapsed() >= t)
+            {
+                return it;
+            }
+
+            if !narrow {
+                narrow = it > broad_iter || schedule.broad_patience.is_some_and(|p| stalled >= p);
+            }
+            if narrow {
+                counter += 1;
+                if improved {
+                    counter = 0;
+                } else if counter >= small_iter {
+                    counter = 0;
+                    self.restore_best();
+                }
+            }
+            if it % interval == 0 {
+                if let Control::Stop = observer.observe(&self.snapshot(it)) {
+                    return it;
+                }
+            }
+        }
+        schedule.max_iter
+    }
+
+    pub fn snapshot(&self, iteration: u64) -> Snapshot<'_> {
+        Snapshot {
+            iteration: iteration,
+            colors: &self.colors,
+            score: self.score,
+            best_score: self.best_colors.0,
+            limiting_score: self.min_score.0,
+            limiting: self.min_score.1,
+        }
+    }
+
+    // Unweighted score, weighted score, and limiting pair/index of every metric, in the order they were given.
+    pub fn get_metric_scores(&self) -> Vec<(f32, f32, Metric)> {
+        self.pair_metrics
+            .iter()
+            .enumerate()
+            .map(|(i, (w, pm))| {
+                let (s, pair_index) = pm.get_min_score();
+                (s, s / w, Metric::Pair(i, pair_index))
+            })
+            .chain(self.constraints.iter().enumerate().map(|(i, (w, c))| {
+                let (s, j) = c.get_min_score();
+                (s, s / w, Metric::Const(i, j))
+            }))
+            .collect()
     }
 
     pub fn get_best_score(&self) -> f32 {
@@ -124,3 +389,93 @@ impl<'a> Optimizer<'a> {
         self.best_colors
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::iter::repeat_with;
+
+    use color_lib::Oklab;
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::lut::{make_lut, Lut, LutStorage};
+    use crate::metric::PairDistance;
+    use crate::score::PairImportance;
+
+    // Four random colors, scored by their Oklab distance.
+    // A cached table, since building a dense one takes minutes in debug builds.
+    fn random_optimizer(lut: &Lut<Oklab>) -> Optimizer<'_> {
+        let colors = repeat_with(|| sRGB(rand::random())).take(4).collect_vec();
+        let pair_distance = PairDistance::new(&colors, 0, PairImportance::uniform(4), lut);
+        Optimizer::new(
+            vec![(1.0, PairMetric::Distance(pair_distance))],
+            vec![],
+            colors,
+            0,
+        )
+    }
+
+    struct StopAfter {
+        interval: u64,
+        count: usize,
+        seen: Vec<u64>,
+    }
+
+    impl Observer for StopAfter {
+        fn interval(&self) -> u64 {
+            self.interval
+        }
+
+        fn observe(&mut self, snapshot: &Snapshot) -> Control {
+            assert!(snapshot.score <= snapshot.best_score);
+            self.seen.push(snapshot.iteration);
+            if self.seen.len() >= self.count {
+                Control::Stop
+            } else {
+                Control::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn test_observer_stop() {
+        let lut = make_lut(LutStorage::Cached, Oklab::from);
+        let mut optimizer = random_optimizer(&lut);
+        let mut observer = StopAfter {
+            interval: 10,
+            count: 3,
+            seen: vec![],
+        };
+        assert_eq!(optimizer.run(&Schedule::new(1000), &mut observer), 30);
+        assert_eq!(observer.seen, vec![10, 20, 30]);
+
+        let mut observer = StopAfter {
+            interval: 0,
+            count: 3,
+            seen: vec![],
+        };
+        assert_eq!(optimizer.run(&Schedule::new(1000), &mut observer), 3);
+        assert_eq!(observer.seen, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_schedule_stop() {
+        let lut = make_lut(LutStorage::Cached, Oklab::from);
+        let mut optimizer = random_optimizer(&lut);
+
+        let mut schedule = Schedule::new(1000000);
+        schedule.target_score = Some(optimizer.get_best_score());
+        // The target is already met.
+        assert_eq!(optimizer.run(&schedule, &mut ()), 0);
+
+        schedule.target_score = Some(optimizer.get_best_score().next_up());
+        // Any improvement reaches the target.
+        let iterations = optimizer.run(&schedule, &mut ());
+        assert!((1..1000000).contains(&iterations));
+
+        schedule.target_score = None;
+        schedule.patience = Some(100);
+        let iterations = optimizer.run(&schedule, &mut ());
+        assert!((100..1000000).contains(&iterations));
+    }
+}
@@ -0,0 +1,63 @@
+// `a` dominates `b` if it is at least as good in every score and strictly better in one.
+fn dominates(a: &Vec<f32>, b: &Vec<f32>) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+// Archive of mutually non-dominated entries, where every score is to be maximized.
+pub struct ParetoArchive<T> {
+    entries: Vec<(Vec<f32>, T)>,
+}
+
+impl<T> ParetoArchive<T> {
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    // Returns whether the entry was added, i.e. nothing in the archive dominates or equals it.
+    pub fn insert(&mut self, scores: Vec<f32>, value: T) -> bool {
+        if self
+            .entries
+            .iter()
+            .any(|(s, _)| *s == scores || dominates(s, &scores))
+        {
+            return false;
+        }
+        self.entries.retain(|(s, _)| !dominates(&scores, s));
+        self.entries.push((scores, value));
+        true
+    }
+
+    pub fn entries(&self) -> &Vec<(Vec<f32>, T)> {
+        &self.entries
+    }
+}
+
+impl<T> Default for ParetoArchive<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive() {
+        let mut archive = ParetoArchive::new();
+        assert!(archive.insert(vec![1.0, 1.0], 'a'));
+        assert!(archive.insert(vec![2.0, 0.5], 'b'));
+        // Dominated by 'a', and equal to 'b'.
+        assert!(!archive.insert(vec![0.5, 0.5], 'c'));
+        assert!(!archive.insert(vec![2.0, 0.5], 'd'));
+        // Dominates 'a' but not 'b'.
+        assert!(archive.insert(vec![1.5, 1.0], 'e'));
+
+        let values = archive
+            .entries()
+            .iter()
+            .map(|(_, v)| *v)
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec!['b', 'e']);
+    }
+}
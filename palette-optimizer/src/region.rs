@@ -0,0 +1,135 @@
+use crate::lut::SrgbLut;
+use color_lib::{sRGB, Gamut, Oklab, Oklch, RgbColor};
+use rand::Rng;
+use serde::Serialize;
+
+// Limits on the Oklch coordinates of a color, on the same 0 to 100 lightness scale as `Oklch`.
+// `None` and an empty `hues` mean no limit.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct OklchBounds {
+    pub lightness: Option<(f32, f32)>,
+    pub max_chroma: Option<f32>,
+    // Allowed hue intervals in degrees, going counterclockwise from the first to the second.
+    // (330, 30) allows reds on both sides of 0.
+    pub hues: Vec<(f32, f32)>,
+}
+
+impl OklchBounds {
+    pub fn contains(&self, c: &Oklch) -> bool {
+        if let Some((min, max)) = self.lightness {
+            if c.L < min || c.L > max {
+                return false;
+            }
+        }
+        if let Some(max) = self.max_chroma {
+            if c.C > max {
+                return false;
+            }
+        }
+        let h = c.h.to_degrees().rem_euclid(360.0);
+        self.hues.is_empty()
+            || self.hues.iter().any(|(from, to)| {
+                let (from, to) = (from.rem_euclid(360.0), to.rem_euclid(360.0));
+                if from <= to {
+                    from <= h && h <= to
+                } else {
+                    h >= from || h <= to
+                }
+            })
+    }
+}
+
+// The colors the optimizer is allowed to use.
+// Moves that would leave the region are rejected, so every color to be optimized must start inside it.
+#[derive(Serialize)]
+pub struct Region {
+    bounds: OklchBounds,
+    #[serde(skip)]
+    gamut: Gamut,
+    has_predicate: bool,
+    #[serde(skip)]
+    allowed: SrgbLut<bool>,
+}
+
+impl Region {
+    pub fn new(bounds: OklchBounds) -> Self {
+        Self::for_gamut(bounds, Gamut::Srgb)
+    }
+
+    // For palettes of 8-bit codes in `gamut`, like `Luts::for_gamut`.
+    pub fn for_gamut(bounds: OklchBounds, gamut: Gamut) -> Self {
+        let allowed =
+            SrgbLut::new(|c| bounds.contains(&Oklab::from(RgbColor::from_8bit(gamut, c)).into()));
+        Self {
+            bounds: bounds,
+            gamut: gamut,
+            has_predicate: false,
+            allowed: allowed,
+        }
+    }
+
+    // Only colors within `bounds` for which `predicate` also holds are allowed.
+    // `predicate` gets the 8-bit codes in `gamut`.
+    pub fn with_predicate(
+        bounds: OklchBounds,
+        gamut: Gamut,
+        predicate: impl Fn(&sRGB) -> bool,
+    ) -> Self {
+        let allowed = SrgbLut::new(|c| {
+            bounds.contains(&Oklab::from(RgbColor::from_8bit(gamut, c)).into()) && predicate(&c)
+        });
+        Self {
+            bounds: bounds,
+            gamut: gamut,
+            has_predicate: true,
+            allowed: allowed,
+        }
+    }
+
+    pub fn gamut(&self) -> Gamut {
+        self.gamut
+    }
+
+    pub fn contains(&self, c: &sRGB) -> bool {
+        self.allowed.get(c)
+    }
+
+    // Uniformly random among the allowed colors.
+    pub fn random_color(&self, rng: &mut impl Rng) -> sRGB {
+        // Even a region of a single color is found in a few tens of millions of tries.
+        for _ in 0..1 << 28 {
+            let c = sRGB(rng.gen());
+            if self.contains(&c) {
+                return c;
+            }
+        }
+        panic!("Region has no colors!")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds() {
+        let bounds = OklchBounds {
+            lightness: Some((20.0, 90.0)),
+            max_chroma: Some(20.0),
+            hues: vec![(330.0, 30.0)],
+        };
+        let c = |l, c, h: f32| Oklch {
+            L: l,
+            C: c,
+            h: h.to_radians(),
+        };
+        assert!(bounds.contains(&c(50.0, 10.0, 0.0)));
+        assert!(bounds.contains(&c(50.0, 10.0, 340.0)));
+        assert!(bounds.contains(&c(50.0, 10.0, -20.0)));
+        assert!(!bounds.contains(&c(50.0, 10.0, 90.0)));
+        assert!(!bounds.contains(&c(10.0, 10.0, 0.0)));
+        assert!(!bounds.contains(&c(95.0, 10.0, 0.0)));
+        assert!(!bounds.contains(&c(50.0, 30.0, 0.0)));
+        assert!(OklchBounds::default().contains(&c(0.0, 0.0, 0.0)));
+    }
+}
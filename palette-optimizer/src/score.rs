@@ -1,6 +1,8 @@
-use std::{f32::INFINITY, fmt::Debug};
+use std::fmt::Debug;
 
+use crate::metric::PairColor;
 use color_lib::{HyAB, Oklab};
+use serde::{Serialize, Serializer};
 
 fn parent(i: usize) -> usize {
     (i - 1) / 2
@@ -14,7 +16,7 @@ fn right(i: usize) -> usize {
     2 * i + 2
 }
 
-pub trait ScoreIndex: Debug + Copy {
+pub(crate) trait ScoreIndex: Debug + Copy {
     fn get(&self) -> usize;
 }
 
@@ -31,7 +33,7 @@ impl ScoreIndex for (usize, usize) {
 }
 
 type ScoreHeap<T> = Vec<(f32, T)>;
-pub struct Scores<T>
+pub(crate) struct Scores<T>
 where
     T: ScoreIndex,
 {
@@ -86,6 +88,11 @@ impl<T: ScoreIndex> Scores<T> {
         self.heap[0]
     }
 
+    // Every score, in no particular order.
+    pub fn get_scores(&self) -> &[(f32, T)] {
+        &self.heap
+    }
+
     pub fn update(&mut self, i: T, val: f32) {
         let index = self.index[i.get()];
         let old_val = self.heap[index].0;
@@ -106,8 +113,8 @@ impl Scores<usize> {
             index: (0..len).collect(),
             heap: Vec::with_capacity(len),
         };
-        for i in 0..len {
-            scores.heap.push((data[i], i));
+        for (i, value) in data.iter().enumerate() {
+            scores.heap.push((*value, i));
             scores.percolate_up(i);
         }
         scores
@@ -122,19 +129,92 @@ impl Scores<(usize, usize)> {
             index: (0..len).collect(),
             heap: Vec::with_capacity(len),
         };
-        for i in 0..len {
-            scores.heap.push((data[i].0, (i, data[i].1)));
+        for (i, (value, j)) in data.iter().enumerate() {
+            scores.heap.push((*value, (i, *j)));
             scores.percolate_up(i);
         }
         scores
     }
 }
 
-pub fn get_pair_score(i: usize, pre_colors: &Vec<Oklab>) -> (f32, usize) {
+// How much each pair of colors matters. A pair's distance is divided by its importance,
+// so a pair with importance 0.5 only needs half the distance, and importance 0 ignores the pair.
+#[derive(Clone, Debug)]
+pub struct PairImportance {
+    n: usize,
+    data: Vec<f32>,
+    // Every pair has importance 1. Pairs are scored in the optimizer's inner loop, where skipping
+    // the lookup and division is noticeably faster.
+    uniform: bool,
+}
+
+impl PairImportance {
+    pub fn uniform(n: usize) -> Self {
+        Self {
+            n: n,
+            data: vec![1.0; n * n],
+            uniform: true,
+        }
+    }
+
+    pub fn new(rows: &Vec<Vec<f32>>) -> Self {
+        let n = rows.len();
+        let mut importance = Self::uniform(n);
+        importance.uniform = rows.iter().flatten().all(|w| *w == 1.0);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.len(), n, "Importance matrix must be square");
+            for (j, w) in row.iter().enumerate() {
+                assert!(*w >= 0.0, "Importance must not be negative");
+                assert_eq!(*w, rows[j][i], "Importance matrix must be symmetric");
+                importance.data[i * n + j] = *w;
+            }
+        }
+        importance
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f32 {
+        self.data[i * self.n + j]
+    }
+
+    pub fn pair_score(&self, i: usize, j: usize, dist: f32) -> f32 {
+        if self.uniform {
+            return dist;
+        }
+        let w = self.get(i, j);
+        if w == 0.0 {
+            f32::INFINITY
+        } else {
+            dist / w
+        }
+    }
+}
+
+// Serialized as rows, like the matrix it was read from.
+impl Serialize for PairImportance {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.data.chunks(self.n.max(1)))
+    }
+}
+
+// Pairs where both colors are below `fixed` are never scored, since neither color can change.
+pub(crate) fn get_pair_score<C: PairColor>(
+    i: usize,
+    pre_colors: &Vec<C>,
+    fixed: usize,
+    importance: &PairImportance,
+) -> (f32, usize) {
     let c = &pre_colors[i];
-    let mut score = (INFINITY, i);
-    for j in (i + 1)..pre_colors.len() {
-        let dist = HyAB(c, &pre_colors[j]);
+    let mut score = (f32::INFINITY, i);
+    for (j, other) in pre_colors.iter().enumerate().skip((i + 1).max(fixed)) {
+        let dist = importance.pair_score(i, j, c.distance(other));
         if dist < score.0 {
             score = (dist, j);
         }
@@ -142,10 +222,69 @@ pub fn get_pair_score(i: usize, pre_colors: &Vec<Oklab>) -> (f32, usize) {
     return score;
 }
 
-pub fn get_pair_scores(pre_colors: &Vec<Oklab>) -> Vec<(f32, usize)> {
+// Steps must increase in lightness. A step that doesn't scores its (non-positive) lightness change instead,
+// so that any ordered palette beats any unordered one.
+pub(crate) fn step_score(c1: &Oklab, c2: &Oklab) -> f32 {
+    if c2.L > c1.L {
+        HyAB(c1, c2)
+    } else {
+        c2.L - c1.L
+    }
+}
+
+// Score of the pair `(i, j)` in an ordered palette whose lightest color is at `midpoint`.
+// Neighbors are steps, going up in lightness before the midpoint and down after it.
+// Any other pair is a color and its mirror on the other side of a diverging palette.
+pub(crate) fn ordered_pair_score(
+    c1: &Oklab,
+    c2: &Oklab,
+    i: usize,
+    j: usize,
+    midpoint: usize,
+) -> f32 {
+    if j != i + 1 {
+        HyAB(c1, c2)
+    } else if i < midpoint {
+        step_score(c1, c2)
+    } else {
+        step_score(c2, c1)
+    }
+}
+
+// Slot `i` holds the step from color `i` to the next. Before the midpoint of a diverging palette,
+// it also holds the distance from color `i` to its mirror, so colors at the same place on the two
+// ramps stay apart, starting with the endpoints. In a sequential palette, the midpoint is the last color.
+pub(crate) fn get_step_score(i: usize, pre_colors: &Vec<Oklab>, midpoint: usize) -> (f32, usize) {
+    let step = (
+        ordered_pair_score(&pre_colors[i], &pre_colors[i + 1], i, i + 1, midpoint),
+        i + 1,
+    );
+    let mirror = pre_colors.len() - 1 - i;
+    if i < midpoint && mirror > midpoint {
+        let dist = HyAB(&pre_colors[i], &pre_colors[mirror]);
+        if dist < step.0 {
+            return (dist, mirror);
+        }
+    }
+    step
+}
+
+pub(crate) fn get_step_scores(pre_colors: &Vec<Oklab>, midpoint: usize) -> Vec<(f32, usize)> {
     let mut scores = Vec::with_capacity(pre_colors.len() - 1);
     for i in 0..(pre_colors.len() - 1) {
-        scores.push(get_pair_score(i, pre_colors));
+        scores.push(get_step_score(i, pre_colors, midpoint));
+    }
+    return scores;
+}
+
+pub(crate) fn get_pair_scores<C: PairColor>(
+    pre_colors: &Vec<C>,
+    fixed: usize,
+    importance: &PairImportance,
+) -> Vec<(f32, usize)> {
+    let mut scores = Vec::with_capacity(pre_colors.len() - 1);
+    for i in 0..(pre_colors.len() - 1) {
+        scores.push(get_pair_score(i, pre_colors, fixed, importance));
     }
     return scores;
 }
@@ -154,6 +293,7 @@ pub fn get_pair_scores(pre_colors: &Vec<Oklab>) -> Vec<(f32, usize)> {
 mod tests {
     use std::iter::repeat_with;
 
+    use color_lib::sRGB;
     use itertools::Itertools;
     use rand::{random, thread_rng, Rng};
 
@@ -186,12 +326,12 @@ mod tests {
                     .unwrap()
             ));
         }
-        for i in 0..len {
+        for (i, value) in data.iter().enumerate() {
             let entry = scores.heap[scores.index[i]];
-            if entry.0 != data[i] || entry.1.get() != i {
+            if entry.0 != *value || entry.1.get() != i {
                 return Err(format!(
                     "Heap has wrong entry! ({}, {}) {:?} {}",
-                    data[i], i, entry, scores.index[i]
+                    value, i, entry, scores.index[i]
                 ));
             }
         }
@@ -213,22 +353,87 @@ mod tests {
     fn heap_test(n: usize) {
         let mut data = repeat_with(random::<f32>).take(n).collect_vec();
         let mut scores = Scores::new(&data);
-        match verify_invariants(&scores, &data) {
-            Err(e) => panic!("{e}"),
-            _ => (),
+        if let Err(e) = verify_invariants(&scores, &data) {
+            panic!("{e}");
         }
         for iter in 0..1000 {
             let i = thread_rng().gen_range(0..n);
             let val = random();
             data[i] = val;
             scores.update(i, val);
-            match verify_invariants(&scores, &data) {
-                Err(e) => panic!("{iter} {i} {val} {e}"),
-                _ => (),
+            if let Err(e) = verify_invariants(&scores, &data) {
+                panic!("{iter} {i} {val} {e}");
             }
         }
     }
 
+    #[test]
+    fn test_pair_scores_fixed() {
+        let colors: Vec<Oklab> = [
+            sRGB([0x00, 0x00, 0x00]),
+            sRGB([0x01, 0x01, 0x01]),
+            sRGB([0xFF, 0xFF, 0xFF]),
+        ]
+        .into_iter()
+        .map(Oklab::from)
+        .collect_vec();
+
+        let importance = PairImportance::uniform(3);
+
+        // Without fixed colors, the two near-blacks are the closest pair.
+        assert_eq!(get_pair_scores(&colors, 0, &importance)[0].1, 1);
+        // With both near-blacks fixed, each may only be compared against white.
+        let scores = get_pair_scores(&colors, 2, &importance);
+        assert_eq!(scores[0].1, 2);
+        assert_eq!(scores[1].1, 2);
+    }
+
+    #[test]
+    fn test_pair_scores_importance() {
+        let colors: Vec<Oklab> = [
+            sRGB([0x00, 0x00, 0x00]),
+            sRGB([0x01, 0x01, 0x01]),
+            sRGB([0xFF, 0xFF, 0xFF]),
+        ]
+        .into_iter()
+        .map(Oklab::from)
+        .collect_vec();
+        let dist = HyAB(&colors[0], &colors[2]);
+
+        // Ignoring the near-black pair leaves white as the closest color to black.
+        let importance = PairImportance::new(&vec![
+            vec![1.0, 0.0, 2.0],
+            vec![0.0, 1.0, 1.0],
+            vec![2.0, 1.0, 1.0],
+        ]);
+        let scores = get_pair_scores(&colors, 0, &importance);
+        assert_eq!(scores[0], (dist / 2.0, 2));
+    }
+
+    #[test]
+    fn test_step_scores() {
+        let colors: Vec<Oklab> = [
+            sRGB([0x00, 0x00, 0x00]),
+            sRGB([0x80, 0x80, 0x80]),
+            sRGB([0x40, 0x40, 0x40]),
+        ]
+        .into_iter()
+        .map(Oklab::from)
+        .collect_vec();
+
+        let scores = get_step_scores(&colors, 2);
+        assert_eq!(scores[0], (HyAB(&colors[0], &colors[1]), 1));
+        // Getting darker is penalized by how much darker it gets.
+        assert_eq!(scores[1], (colors[2].L - colors[1].L, 2));
+        assert!(scores[1].0 < 0.0);
+
+        // Diverging around the middle color, getting darker is right, and black is closer to
+        // its mirror than to the next step.
+        let scores = get_step_scores(&colors, 1);
+        assert_eq!(scores[0], (HyAB(&colors[0], &colors[2]), 2));
+        assert_eq!(scores[1], (HyAB(&colors[1], &colors[2]), 2));
+    }
+
     #[test]
     fn test_heap() {
         for p in 0..5 {
@@ -239,9 +444,8 @@ mod tests {
         for n in 1..1000 {
             let data = repeat_with(random::<f32>).take(n).collect_vec();
             let scores = Scores::new(&data);
-            match verify_invariants(&scores, &data) {
-                Err(e) => panic!("{n} {e}"),
-                _ => (),
+            if let Err(e) = verify_invariants(&scores, &data) {
+                panic!("{n} {e}");
             }
         }
     }
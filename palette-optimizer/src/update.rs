@@ -1,9 +1,11 @@
 use itertools::{iproduct, Itertools};
 use once_cell::sync::Lazy;
-use rand::{distributions, distributions::Distribution, thread_rng};
+use rand::{distributions, distributions::Distribution, Rng};
 
 use color_lib::sRGB;
 
+use crate::region::Region;
+
 struct ColorPairUpdate {
     which: Which,
     cu: ColorUpdate,
@@ -53,9 +55,22 @@ static UPDATE_SLICE: Lazy<Vec<ColorUpdate>> = Lazy::new(|| {
 static UPDATE_DISTRIBUTION: Lazy<distributions::Slice<'static, ColorUpdate>> =
     Lazy::new(|| distributions::Slice::new(UPDATE_SLICE.as_slice()).expect("Slice empty"));
 
-pub fn update_color(colors: &Vec<sRGB>, i: usize) -> sRGB {
-    let cu = UPDATE_DISTRIBUTION.sample(&mut thread_rng());
-    color_update(colors[i], cu)
+// A move that would leave `region` is rejected, leaving the color where it was.
+fn constrain(old: sRGB, new: sRGB, region: Option<&Region>) -> sRGB {
+    match region {
+        Some(r) if !r.contains(&new) => old,
+        _ => new,
+    }
+}
+
+pub fn update_color(
+    colors: &Vec<sRGB>,
+    i: usize,
+    region: Option<&Region>,
+    rng: &mut impl Rng,
+) -> sRGB {
+    let cu = UPDATE_DISTRIBUTION.sample(rng);
+    constrain(colors[i], color_update(colors[i], cu), region)
 }
 
 static UPDATE_PAIR_SLICE: Lazy<Vec<ColorPairUpdate>> = Lazy::new(|| {
@@ -73,11 +88,17 @@ static UPDATE_PAIR_SLICE: Lazy<Vec<ColorPairUpdate>> = Lazy::new(|| {
 static UPDATE_PAIR_DISTRIBUTION: Lazy<distributions::Slice<'static, ColorPairUpdate>> =
     Lazy::new(|| distributions::Slice::new(UPDATE_PAIR_SLICE.as_slice()).expect("Slice empty"));
 
-pub fn update_color_pair(colors: &Vec<sRGB>, (i, j): (usize, usize)) -> (usize, sRGB) {
-    let cu = UPDATE_PAIR_DISTRIBUTION.sample(&mut thread_rng());
+pub fn update_color_pair(
+    colors: &Vec<sRGB>,
+    (i, j): (usize, usize),
+    region: Option<&Region>,
+    rng: &mut impl Rng,
+) -> (usize, sRGB) {
+    let cu = UPDATE_PAIR_DISTRIBUTION.sample(rng);
     let index = match cu.which {
         Which::First => i,
         Which::Second => j,
     };
-    (index, color_update(colors[index], &cu.cu))
+    let c = color_update(colors[index], &cu.cu);
+    (index, constrain(colors[index], c, region))
 }
@@ -1,8 +1,7 @@
 extern crate color_lib;
 
-use color_lib::{sRGB, to_string};
+use color_lib::sRGB;
 use std::f64::consts::{PI, TAU};
-use std::f64::INFINITY;
 use svg::node::element::path::Data;
 use svg::node::element::Path;
 use svg::node::Value;
@@ -156,7 +155,7 @@ fn squareness_objective((inner_radius, outer_radius): (f64, f64), n: usize) -> f
 }
 
 fn mean_squareness(list: &Vec<usize>) -> f64 {
-    let radii = calculate_radii(&list, 1.0);
+    let radii = calculate_radii(list, 1.0);
     let mut total = 0.0;
     for i in 0..list.len() {
         total += list[i] as f64 * squareness_objective((radii[i], radii[i + 1]), list[i]);
@@ -175,7 +174,7 @@ fn add_to_list(
         let sq = mean_squareness(&inner_list);
         (inner_list, sq)
     } else {
-        let (lower_bound, upper_bound) = if list.len() == 0 {
+        let (lower_bound, upper_bound) = if list.is_empty() {
             // This is guaranteed to be true,
             // since [1, 5] has a better score than [6]
             // despite taking the same area
@@ -190,7 +189,7 @@ fn add_to_list(
             }
         };
         let mut best_list = vec![];
-        let mut best_score = INFINITY;
+        let mut best_score = f64::INFINITY;
         // This loop can run zero times, and that is intentional.
         for i in
             lower_bound..=upper_bound.min((n_remaining + layers_remaining) / (layers_remaining + 1))
@@ -242,7 +241,7 @@ fn angle_offset(n: usize, m: usize) -> f64 {
         return PI / n as f64;
     }
     let (gcd, lcm) = num_integer::gcd_lcm(n, m);
-    if n % 2 == 0 || m % 2 == 0 {
+    if n.is_multiple_of(2) || m.is_multiple_of(2) {
         (PI / lcm as f64) + (PI / gcd as f64) + (TAU / n.max(m) as f64)
     } else {
         (TAU / lcm as f64) + (PI / gcd as f64) + (TAU / n.min(m) as f64)
@@ -270,7 +269,7 @@ fn colors_to_strings(colors: &Vec<sRGB>, ring_sizes: &Vec<usize>) -> Vec<Vec<Str
         output.push(
             colors[start_index..start_index + ring]
                 .iter()
-                .map(to_string)
+                .map(sRGB::to_string)
                 .collect(),
         );
         start_index += ring;
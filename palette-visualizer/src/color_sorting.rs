@@ -58,7 +58,7 @@ impl<T> PairMatrix<T> {
     }
 
     fn inverse_triangle(tri: usize) -> usize {
-        (1 + (1 + 8 * tri).sqrt()) / 2
+        (1 + 8 * tri).sqrt().div_ceil(2)
     }
 
     fn ordered_pair_to_index((min, max): (usize, usize)) -> usize {
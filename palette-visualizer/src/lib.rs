@@ -1,8 +1,10 @@
 mod code;
 mod color_sorting;
+mod scatter;
 
 pub use code::make_document;
 use color_lib::sRGB;
+pub use scatter::make_scatter_document;
 
 const RADIUS: f64 = 300.0;
 const DELTA: f64 = RADIUS * 0.02;
@@ -15,3 +17,16 @@ where
 
     svg::save(path, &document)
 }
+
+pub fn save_scatter<T>(
+    path: T,
+    names: &[&str],
+    points: &Vec<(Vec<f32>, Vec<sRGB>)>,
+) -> Result<(), std::io::Error>
+where
+    T: std::convert::AsRef<std::path::Path>,
+{
+    let document = make_scatter_document(names, points);
+
+    svg::save(path, &document)
+}
@@ -0,0 +1,96 @@
+use color_lib::sRGB;
+use svg::node::element::{Rectangle, Text};
+use svg::Document;
+
+const PANEL: f64 = 200.0;
+const MARGIN: f64 = 30.0;
+const SWATCH: f64 = 3.0;
+
+fn axis_range(points: &Vec<(Vec<f32>, Vec<sRGB>)>, axis: usize) -> (f64, f64) {
+    let (min, max) = points
+        .iter()
+        .map(|(scores, _)| scores[axis] as f64)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), s| {
+            (min.min(s), max.max(s))
+        });
+    if max > min {
+        (min, max)
+    } else {
+        // A single point, or all points equal. Center them.
+        (min - 1.0, max + 1.0)
+    }
+}
+
+fn make_label(x: f64, y: f64, text: &str) -> Text {
+    Text::new(text)
+        .set("x", x)
+        .set("y", y)
+        .set("font-size", 12)
+        .set("font-family", "sans-serif")
+        .set("text-anchor", "middle")
+}
+
+// Each point is drawn as a strip of its palette's colors.
+fn make_point(x: f64, y: f64, colors: &Vec<sRGB>) -> Vec<Rectangle> {
+    let width = SWATCH * colors.len() as f64;
+    colors
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            Rectangle::new()
+                .set("x", x - width / 2.0 + SWATCH * i as f64)
+                .set("y", y - SWATCH)
+                .set("width", SWATCH)
+                .set("height", 2.0 * SWATCH)
+                .set("fill", c.to_string())
+        })
+        .collect()
+}
+
+// Scatter plot matrix of every pair of scores, with one panel per pair.
+// `points` holds each palette's scores, in the same order as `names`.
+pub fn make_scatter_document(names: &[&str], points: &Vec<(Vec<f32>, Vec<sRGB>)>) -> Document {
+    let n = names.len();
+    let ranges = (0..n).map(|a| axis_range(points, a)).collect::<Vec<_>>();
+    let size = (n - 1) as f64 * (PANEL + MARGIN) + MARGIN;
+    let mut document = Document::new().set("viewBox", (0.0, 0.0, size, size));
+
+    for row in 0..n - 1 {
+        for col in row + 1..n {
+            // x is the column's score, y is the row's score.
+            let left = MARGIN + (col - 1) as f64 * (PANEL + MARGIN);
+            let top = MARGIN + row as f64 * (PANEL + MARGIN);
+            document = document
+                .add(
+                    Rectangle::new()
+                        .set("x", left)
+                        .set("y", top)
+                        .set("width", PANEL)
+                        .set("height", PANEL)
+                        .set("fill", "none")
+                        .set("stroke", "#888888"),
+                )
+                .add(make_label(
+                    left + PANEL / 2.0,
+                    top + PANEL + 14.0,
+                    names[col],
+                ))
+                .add(make_label(left - 8.0, top + PANEL / 2.0, names[row]).set(
+                    "transform",
+                    format!("rotate(-90 {} {})", left - 8.0, top + PANEL / 2.0),
+                ));
+
+            let (x_min, x_max) = ranges[col];
+            let (y_min, y_max) = ranges[row];
+            for (scores, colors) in points {
+                let x = left + PANEL * (scores[col] as f64 - x_min) / (x_max - x_min);
+                let y = top + PANEL * (1.0 - (scores[row] as f64 - y_min) / (y_max - y_min));
+                for rect in make_point(x, y, colors) {
+                    document = document.add(rect);
+                }
+            }
+        }
+    }
+
+    document
+}